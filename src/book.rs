@@ -0,0 +1,160 @@
+use std::fs;
+use rand::Rng;
+use rustc_hash::FxHashMap;
+use crate::bot::ShobuBot;
+use crate::packed_move::PackedMove;
+use crate::search_limits::SearchLimits;
+use crate::search_result::Score;
+use crate::shobu::{Shobu, BLACK, WHITE};
+use crate::shobu_move::Move;
+use crate::symmetry;
+
+const MAGIC: &[u8; 4] = b"SHBK";
+
+// moves are stored for position with variation hash, other symmetric positions transform them
+struct BookEntry {
+    variation_hash: u64,
+    moves: Vec<(Move, u16)>
+}
+
+// keyed by symmetry invariant hash
+pub struct OpeningBook {
+    entries: FxHashMap<u64, BookEntry>
+}
+
+// boards in ascending order, so equal moves have equal encoding
+fn normalized(mv: &Move) -> Move {
+    let mut mv = mv.deep_copy();
+    if mv.board_1 > mv.board_2 {
+        std::mem::swap(&mut mv.board_1, &mut mv.board_2);
+        std::mem::swap(&mut mv.from_1, &mut mv.from_2);
+    }
+    mv
+}
+
+fn take<const N: usize>(rest: &mut &[u8]) -> Result<[u8; N], String> {
+    if rest.len() < N { return Err("Opening book is truncated!".to_string()); }
+    let (bytes, tail) = rest.split_at(N);
+    *rest = tail;
+    Ok(bytes.try_into().unwrap())
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        OpeningBook { entries: FxHashMap::default() }
+    }
+
+    pub fn position_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn contains(&self, position: &Shobu) -> bool {
+        self.entries.contains_key(&position.get_hash())
+    }
+
+    pub fn add_move(&mut self, position: &Shobu, mv: &Move, weight: u16) {
+        let entry = self.entries.entry(position.get_hash()).or_insert(BookEntry {
+            variation_hash: position.get_symmetry_hash(false, false),
+            moves: Vec::new()
+        });
+        let Some((color_swap, horizontal_swap)) = symmetry::matching_symmetries(position, entry.variation_hash) else { return; };
+        let mv = normalized(&mv.to_symmetric(color_swap, horizontal_swap));
+        match entry.moves.iter_mut().find(|(x, _)| PackedMove::new(x) == PackedMove::new(&mv)) {
+            Some((_, old_weight)) => *old_weight = old_weight.saturating_add(weight),
+            None => entry.moves.push((mv, weight))
+        }
+    }
+
+    // random book move with probability proportional to its weight
+    pub fn probe<R: Rng>(&self, position: &Shobu, rng: &mut R) -> Option<Move> {
+        let entry = self.entries.get(&position.get_hash())?;
+        let (color_swap, horizontal_swap) = symmetry::matching_symmetries(position, entry.variation_hash)?;
+        // only legal moves are picked from, so invalid entry never hides valid ones
+        let moves: Vec<(Move, u32)> = entry.moves.iter()
+            .map(|(mv, weight)| (mv.to_symmetric(color_swap, horizontal_swap), *weight as u32))
+            .filter(|(mv, _)| position.validate_and_extend(mv).is_ok())
+            .collect();
+        let total: u32 = moves.iter().map(|(_, weight)| weight).sum();
+        if total == 0 { return None; }
+        let mut pick = rng.gen_range(0..total);
+        for (mv, weight) in moves {
+            if pick < weight { return Some(mv); }
+            pick -= weight;
+        }
+        None
+    }
+
+    // searches every book line up to plies deep, best of width moves gets the highest weight
+    pub fn build(&mut self, bot: &mut ShobuBot, position: &mut Shobu, plies: usize, width: usize, limits: &SearchLimits) {
+        if plies == 0 || position.winner != 0 || self.contains(position) { return; }
+        // position without legal move ends line
+        let Ok(lines) = bot.analyse(position, limits, width) else { return; };
+        for (rank, line) in lines.iter().enumerate() {
+            if rank > 0 && matches!(line.score, Score::LossIn(_)) { break; }
+            let mv = Move::from_string(&line.mv, position.active_player).unwrap();
+            self.add_move(position, &mv, (width - rank) as u16);
+            position.make_move_unsafe(&mv);
+            self.build(bot, position, plies - 1, width, limits);
+            position.undo_move();
+        }
+    }
+
+    // adds first plies moves of winners from game records "opening;black;white;winner;moves"
+    pub fn add_games(&mut self, records: &str, plies: usize) -> Result<(), String> {
+        for line in records.lines().filter(|x| !x.trim().is_empty()) {
+            let fields: Vec<&str> = line.split(';').collect();
+            if fields.len() != 5 { return Err(format!("Invalid game record {}!", line)); }
+            let winner = match fields[3] { "b" => BLACK, "w" => WHITE, _ => continue };
+            let mut position = Shobu::from_string(fields[0]);
+            for encoded in fields[4].split_whitespace().take(plies) {
+                let mv = Move::from_string(encoded, position.active_player).ok_or(format!("Invalid move {}!", encoded))?;
+                if position.active_player == winner {
+                    self.add_move(&position, &mv, 1);
+                }
+                position.make_move(&mv)?;
+            }
+        }
+        Ok(())
+    }
+
+    // magic, then per entry: key, variation hash, move count and encoded moves with weights, little endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for (key, entry) in &self.entries {
+            bytes.extend(key.to_le_bytes());
+            bytes.extend(entry.variation_hash.to_le_bytes());
+            bytes.extend((entry.moves.len() as u16).to_le_bytes());
+            for (mv, weight) in &entry.moves {
+                bytes.extend(PackedMove::new(mv).bits().to_le_bytes());
+                bytes.extend(weight.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(MAGIC) { return Err("Not an opening book!".to_string()); }
+        let mut book = OpeningBook::new();
+        let mut rest = &bytes[MAGIC.len()..];
+        while !rest.is_empty() {
+            let key = u64::from_le_bytes(take::<8>(&mut rest)?);
+            let variation_hash = u64::from_le_bytes(take::<8>(&mut rest)?);
+            let count = u16::from_le_bytes(take::<2>(&mut rest)?);
+            let mut moves = Vec::new();
+            for _ in 0..count {
+                let mv = PackedMove::from_bits(u16::from_le_bytes(take::<2>(&mut rest)?)).to_move();
+                moves.push((mv, u16::from_le_bytes(take::<2>(&mut rest)?)));
+            }
+            book.entries.insert(key, BookEntry { variation_hash, moves });
+        }
+        Ok(book)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_bytes(&fs::read(path).map_err(|e| e.to_string())?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+}
@@ -2,149 +2,505 @@ use crate::shobu::*;
 use crate::bot_constants::*;
 use crate::shobu_move::Move;
 use crate::shobu_move::MoveExtended;
+use crate::move_list::StagedMoves;
+use crate::book::OpeningBook;
+use crate::tablebase::Tablebases;
+use crate::nnue::Network;
 use crate::symmetry;
+use crate::eval_params::EvalParams;
+use crate::eval_breakdown::EvalBreakdown;
+use crate::search_limits::SearchLimits;
+use crate::search_stats::SearchStats;
+use crate::search_result::{moves_to_string, AnalysisLine, Score, SearchResult};
+use crate::time_manager::{GameClock, TimeManager};
+use crate::transposition_table::TranspositionTable;
 use crate::tt_entry::TTEntry;
-use rustc_hash::FxHashMap;
 use std::io;
 use std::iter::zip;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, JoinHandle};
 use std::time::SystemTime;
 use crate::utils;
 use crate::tt_entry::*;
 use std::collections::LinkedList;
 
 pub struct ShobuBot {
-    ms_per_move: u128,
     use_time_percentage: f64,
-    tt: FxHashMap<u64, TTEntry>,
+    tt: Arc<TranspositionTable>,
     max_depth: usize,
-    psts: [[f64; 36]; 2],
+    params: EvalParams,
     tt_size: usize,
-    negamax_calls: usize
+    // searched nodes of all threads, shared with helpers for node limit
+    nodes: Arc<AtomicUsize>,
+    threads: usize,
+    stop: Arc<AtomicBool>,
+    limits: SearchLimits,
+    time_manager: Option<TimeManager>,
+    ponder: bool,
+    pondering: Option<Ponder>,
+    // set on ponder hit, background search continues with limits of real one
+    hit_limits: Option<Arc<HitLimits>>,
+    root_ply: usize,
+    multipv: usize,
+    stats: SearchStats,
+    book: Option<Arc<OpeningBook>>,
+    // replaces handcrafted eval when set
+    network: Option<Arc<Network>>,
+    tablebases: Option<Arc<Tablebases>>
+}
+
+type HitLimits = OnceLock<(SearchLimits, Option<TimeManager>)>;
+
+// background search of position after expected opponent reply
+struct Ponder {
+    expected_position: String,
+    stop: Arc<AtomicBool>,
+    hit_limits: Arc<HitLimits>,
+    handle: JoinHandle<Option<SearchResult>>
 }
 
 impl ShobuBot {
     pub fn new() -> Self {
+        Self::with_tt_size(TT_SIZE)
+    }
+
+    // only evaluates positions, so transposition table has single slot
+    pub fn evaluator(params: EvalParams) -> Self {
+        let mut bot = Self::with_tt_size(1);
+        bot.set_params(params);
+        bot
+    }
+
+    fn with_tt_size(tt_size: usize) -> Self {
         ShobuBot {
-            ms_per_move: MS_PER_MOVE,
             use_time_percentage: USE_TIME_PERCENTAGE,
             max_depth: MAX_DEPTH,
-            psts: PSTS,
-            tt_size: TT_SIZE,
-            tt: FxHashMap::default(),
-            negamax_calls: 0
+            params: EvalParams::new(),
+            tt_size,
+            tt: Arc::new(TranspositionTable::new(tt_size)),
+            nodes: Arc::new(AtomicUsize::new(0)),
+            threads: THREADS,
+            stop: Arc::new(AtomicBool::new(false)),
+            limits: SearchLimits::new(),
+            time_manager: None,
+            ponder: false,
+            pondering: None,
+            hit_limits: None,
+            root_ply: 0,
+            multipv: 1,
+            stats: SearchStats::default(),
+            book: None,
+            network: None,
+            tablebases: None
+        }
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = usize::max(threads, 1);
+    }
+
+    pub fn set_params(&mut self, params: EvalParams) {
+        self.params = params;
+    }
+
+    pub fn params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    pub fn set_book(&mut self, book: Option<Arc<OpeningBook>>) {
+        self.book = book;
+    }
+
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.network = network;
+    }
+
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
+
+    // forgets positions of previous games
+    pub fn clear_hash(&mut self) {
+        self.stop_pondering();
+        self.tt.clear();
+    }
+
+    pub fn set_ponder(&mut self, ponder: bool) {
+        self.ponder = ponder;
+        if !ponder {
+            self.stop_pondering();
+        }
+    }
+
+    // search state for helper thread, shares transposition table and stop flag
+    fn helper(&self) -> Self {
+        ShobuBot {
+            use_time_percentage: self.use_time_percentage,
+            max_depth: self.max_depth,
+            params: self.params.clone(),
+            tt_size: self.tt_size,
+            tt: Arc::clone(&self.tt),
+            nodes: Arc::clone(&self.nodes),
+            threads: 1,
+            stop: Arc::clone(&self.stop),
+            limits: self.limits.clone(),
+            time_manager: None,
+            ponder: false,
+            pondering: None,
+            hit_limits: None,
+            root_ply: 0,
+            multipv: 1,
+            stats: SearchStats::default(),
+            book: None,
+            network: self.network.clone(),
+            tablebases: self.tablebases.clone()
         }
     }
 
     pub fn play_game(&mut self) {
         let stdin = io::stdin();
         loop {
-            let _ = utils::input(&stdin);
+            let clock = GameClock::from_string(&utils::input(&stdin));
             let position = utils::input(&stdin);
             let mut game = Shobu::from_string(&position);
-            let mv = self.choose_move(&mut game);
+            let limits = match clock {
+                Some(clock) => SearchLimits::clock(clock),
+                None => SearchLimits::new()
+            };
+            let mv = self.choose_move(&mut game, &limits);
             println!("{}", game.validate_and_extend(&mv).unwrap().to_string(game.active_player));
+            if self.ponder {
+                self.start_pondering(&game, &mv);
+            }
         };
     }
 
-    pub fn choose_move(&mut self, position: &mut Shobu) -> Move {
-        let start_time = SystemTime::now();
-        let mut best_move = 0;
-        let moves = position.get_legal_moves();
-        'iteration: for depth  in 1..=self.max_depth {
-            let mut iteration_best = 0;
-            let mut best_eval = -INF;
-            for i in 0..moves.len() {
-                position.make_move_unsafe(&moves[i].mv);
-                let eval = -self.negamax(position, depth-1, -INF, INF, position.active_player, start_time);
-                position.undo_move();
-                if eval > best_eval {
-                    best_eval = eval;
-                    iteration_best = i;
-                }
-                if self.time_is_ending(start_time) 
-                {
-                    break 'iteration;
-                }
-            }
-            best_move = iteration_best;
+    // searches expected opponent reply on own stop flag until choose_move is called, returns pondered reply
+    pub fn start_pondering(&mut self, position: &Shobu, mv: &Move) -> Option<Move> {
+        self.stop_pondering();
+        let mut ponder_position = position.clone();
+        let pv = self.principal_variation(&mut ponder_position, mv, 2);
+        let reply = pv.get(1)?.deep_copy();
+        ponder_position.make_move_unsafe(mv);
+        ponder_position.make_move_unsafe(&reply);
+        if ponder_position.winner != 0 { return None; }
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut ponder_bot = self.helper();
+        ponder_bot.threads = self.threads;
+        ponder_bot.stop = Arc::new(AtomicBool::new(false));
+        ponder_bot.nodes = Arc::new(AtomicUsize::new(0));
+        let hit_limits = Arc::new(OnceLock::new());
+        ponder_bot.hit_limits = Some(Arc::clone(&hit_limits));
+        let limits = SearchLimits::infinite(Arc::clone(&stop));
+        let expected_position = ponder_position.to_string();
+        let handle = thread::spawn(move || ponder_bot.search(&mut ponder_position, &limits).ok());
+        self.pondering = Some(Ponder { expected_position, stop, hit_limits, handle });
+        Some(reply)
+    }
+
+    pub fn stop_pondering(&mut self) {
+        if let Some(ponder) = self.pondering.take() {
+            ponder.stop.store(true, Ordering::Relaxed);
+            let _ = ponder.handle.join();
+        }
+    }
+
+    // returns whether pondered position was reached, with its result when time budget was spent on it
+    // on miss or without time limit search stops keeping table entries
+    fn finish_pondering(&mut self, position: &Shobu, limits: &SearchLimits) -> (bool, Option<SearchResult>) {
+        let Some(ponder) = self.pondering.take() else { return (false, None); };
+        let hit = ponder.expected_position == position.to_string();
+        let timed = limits.movetime.is_some() || limits.clock.is_some();
+        if hit && timed {
+            // search stops on real conditions, time spent pondering counts into move budget
+            let time_manager = limits.clock.map(|clock| TimeManager::new(&clock, position.active_player));
+            let _ = ponder.hit_limits.set((limits.clone(), time_manager));
+        } else {
+            ponder.stop.store(true, Ordering::Relaxed);
+        }
+        let result = ponder.handle.join().ok().flatten();
+        if !hit || !timed { return (hit, None); }
+        // no finished iteration, search again on filled table
+        (true, result.filter(|x| x.depth > 0).map(|x| SearchResult { ponder_hit: true, ..x }))
+    }
+
+    // moves stored in transposition table starting with given move
+    pub fn principal_variation(&self, position: &mut Shobu, first: &Move, max_length: usize) -> Vec<Move> {
+        let mut pv = vec![first.deep_copy()];
+        position.make_move_unsafe(first);
+        while pv.len() < max_length && position.winner == 0 {
+            let Some(entry) = self.tt.get(position) else { break; };
+            let Some((color_swap, horizontal_swap)) = symmetry::transposition_symmetries(position, &entry) else { break; };
+            let mv = entry.best_move.to_move().to_symmetric(color_swap, horizontal_swap);
+            if position.validate_and_extend(&mv).is_err() { break; }
+            position.make_move_unsafe(&mv);
+            pv.push(mv);
+        }
+        for _ in 0..pv.len() {
+            position.undo_move();
         }
-        moves[best_move].mv.deep_copy()
+        pv
     }
 
-    fn time_is_ending(&self, start_time: SystemTime) -> bool {
-        match start_time.elapsed() {
-            Ok(elapsed) => {
-                elapsed.as_millis() > (self.ms_per_move as f64 * self.use_time_percentage) as u128 
+    // position must have legal move
+    pub fn choose_move(&mut self, position: &mut Shobu, limits: &SearchLimits) -> Move {
+        self.search(position, limits).unwrap().best_move
+    }
+
+    // error when no legal move is left after search and exclude moves of limits
+    pub fn search(&mut self, position: &mut Shobu, limits: &SearchLimits) -> Result<SearchResult, String> {
+        // every requested move is scored or excluded, so none may be illegal
+        for mv in limits.search_moves.iter().flatten().chain(&limits.exclude_moves) {
+            if position.validate_and_extend(mv).is_err() {
+                return Err(format!("Invalid move {}!", MoveExtended::new(mv, false, false).to_string(position.active_player)));
             }
-            _ => true
         }
+        let (ponder_hit, pondered) = self.finish_pondering(position, limits);
+        if let Some(result) = pondered {
+            return Ok(result);
+        }
+        if let Some(result) = self.book_move(position, limits) {
+            return Ok(result);
+        }
+        let start_time = SystemTime::now();
+        self.limits = limits.clone();
+        if self.root_moves(position).is_empty() {
+            return Err("No legal move left to search!".to_string());
+        }
+        if self.network.is_some() && !self.network_in_position(position) {
+            position.set_network(self.network.clone());
+        }
+        self.stop.store(false, Ordering::Relaxed);
+        self.time_manager = limits.clock.map(|clock| TimeManager::new(&clock, position.active_player));
+        self.nodes.store(0, Ordering::Relaxed);
+        // lazy smp, helpers fill shared table searching the same root at varied depths
+        thread::scope(|scope| {
+            for id in 1..self.threads {
+                let mut helper = self.helper();
+                let mut helper_position = position.clone();
+                scope.spawn(move || {
+                    helper.iterative_deepening(&mut helper_position, start_time, 1 + id % 2);
+                });
+            }
+            let result = self.iterative_deepening(position, start_time, 1);
+            self.stop.store(true, Ordering::Relaxed);
+            Ok(SearchResult { ponder_hit, ..result })
+        })
     }
 
-    fn mobility_score(&self, active_player: i8, position: &Shobu) -> f64 {
-        let (m1, m2) = 
-            if active_player == BLACK
-                {(position.available_passive_directions(0, -1), position.available_passive_directions(1, -1))}
-            else 
-                {(position.available_passive_directions(2, 1), position.available_passive_directions(3, 1))};
-        self.board_mobility_score(m1) + self.board_mobility_score(m2)
+    // weighted random book move, only when search is not restricted to analysis of given moves
+    fn book_move(&self, position: &Shobu, limits: &SearchLimits) -> Option<SearchResult> {
+        if self.multipv > 1 || limits.infinite || limits.search_moves.is_some() || !limits.exclude_moves.is_empty() { return None; }
+        let mv = self.book.as_ref()?.probe(position, &mut rand::thread_rng())?;
+        Some(SearchResult {
+            best_move: mv.deep_copy(),
+            eval: 0.0,
+            depth: 0,
+            pv: vec![mv.deep_copy()],
+            lines: vec![(mv, 0.0)],
+            root_moves: Vec::new(),
+            iterations: Vec::new(),
+            ponder_hit: false
+        })
     }
 
-    fn board_mobility_score(&self, mobility: usize) -> f64 {
-        if mobility < 7 {return -10.0 + 0.7 * (mobility as f64);}
-        else if mobility < 13 {return 0.7 * mobility as f64;}
-        else {return 8.4 + 0.5 * (mobility as f64 - 12.0)};
+    // top root moves ranked by evals of one root search per iteration
+    pub fn analyse(&mut self, position: &mut Shobu, limits: &SearchLimits, multipv: usize) -> Result<Vec<AnalysisLine>, String> {
+        self.multipv = usize::max(multipv, 1);
+        let result = self.search(position, limits);
+        self.multipv = 1;
+        let result = result?;
+        Ok(result.lines.iter().map(|(mv, eval)| {
+            let pv = self.principal_variation(position, mv, usize::max(result.depth, 1));
+            AnalysisLine {
+                mv: position.validate_and_extend(mv).unwrap().to_string(position.active_player),
+                score: Score::from_eval(*eval),
+                pv: moves_to_string(position, &pv),
+                depth: result.depth
+            }
+        }).collect())
     }
 
-    fn eval(&self, position: &Shobu) -> f64 {
-        let mut eval = 0.0;
-        // black pieces
-        for piece_list in position.pieces[0] {
-            let mut material = 0;
-            for tile in piece_list {
-                if tile == NOT_ON_BOARD { continue; }
-                eval -= self.psts[0][tile];
-                material += 1;
+    fn iterative_deepening(&mut self, position: &mut Shobu, start_time: SystemTime, first_depth: usize) -> SearchResult {
+        // ranked root moves of last finished iteration
+        let mut lines: Vec<(usize, f64)> = Vec::new();
+        let mut result_depth = 0;
+        self.root_ply = position.history.len();
+        let moves = self.root_moves(position);
+        let mut root_evals: Vec<(usize, f64)> = Vec::new();
+        let multipv = usize::min(self.multipv, moves.len());
+        let max_depth = usize::min(self.max_depth, self.limits.depth.unwrap_or(self.max_depth));
+        let mut iterations: Vec<SearchStats> = Vec::new();
+        for depth in first_depth..=max_depth {
+            let iteration_start = start_time.elapsed().map_or(0, |x| x.as_millis());
+            let iteration_start_nodes = self.nodes.load(Ordering::Relaxed);
+            self.stats = SearchStats::default();
+            let Some(evals) = self.search_root(position, &moves, depth, start_time) else { break; };
+            // every root move is searched with full window, its eval is exact
+            let mut ranked = evals.clone();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+            ranked.truncate(multipv);
+            lines = ranked;
+            root_evals = evals;
+            let total_time = start_time.elapsed().map_or(0, |x| x.as_millis());
+            let mut stats = self.stats.clone();
+            stats.depth = depth;
+            stats.nodes = self.nodes.load(Ordering::Relaxed) - iteration_start_nodes;
+            stats.time = total_time - iteration_start;
+            stats.total_time = total_time;
+            stats.branching_factor = iterations.last().map_or(0.0, |x| stats.nodes as f64 / usize::max(x.nodes, 1) as f64);
+            iterations.push(stats);
+            let second_eval = root_evals.iter().filter(|x| x.0 != lines[0].0).fold(-INF, |second, x| f64::max(second, x.1));
+            result_depth = depth;
+            if let Some((limits, time_manager)) = self.handed_limits().map(|(x, y)| (x.clone(), y.cloned())) {
+                self.limits = limits;
+                self.time_manager = time_manager;
             }
-            eval -= MATERIAL[material];
-        }
-        eval -= self.mobility_score(BLACK, position);
-        // white pieces
-        for piece_list in position.pieces[1] {
-            let mut material = 0;
-            for tile in piece_list {
-                if tile == NOT_ON_BOARD { continue; }
-                eval += self.psts[1][tile];
-                material += 1;
+            if let Some(time_manager) = &mut self.time_manager {
+                let elapsed = start_time.elapsed().map_or(u128::MAX, |x| x.as_millis());
+                if !time_manager.continue_search(elapsed, lines[0].0, lines[0].1, second_eval) { break; }
             }
-            eval += MATERIAL[material];
         }
-        eval += self.mobility_score(WHITE, position);
-        eval
+        // without finished iteration first root move is played and no line is reported
+        let (best_move, eval) = lines.first().map_or((0, 0.0), |&x| x);
+        SearchResult {
+            best_move: moves[best_move].mv.deep_copy(),
+            eval,
+            depth: result_depth,
+            pv: self.principal_variation(position, &moves[best_move].mv, usize::max(result_depth, 1)),
+            lines: lines.iter().map(|&(i, eval)| (moves[i].mv.deep_copy(), eval)).collect(),
+            root_moves: root_evals.iter().map(|&(i, eval)| (moves[i].mv.deep_copy(), eval)).collect(),
+            iterations,
+            ponder_hit: false
+        }
     }
 
-    fn moves_ordered(&mut self, position: &mut Shobu) -> Vec<MoveExtended> {
-        let mut moves = position.get_legal_moves();
-        moves.sort_by_key(|x| if x.push_1 || x.push_2 {0} else {1} );
-        match self.tt.get(&position.get_hash()) {
-            Some(entry) => {
-                match symmetry::transposition_symmetries(&position, entry) {
-                    Some((color_swap, horizontal_swap)) => {
-                        let tt_best = entry.best_move.to_symmetric(color_swap, horizontal_swap);
-                        match position.validate_and_extend(&tt_best) {
-                            Ok(mv) => {
-                                moves.push(mv);
-                                let last = moves.len() - 1;
-                                moves.swap(0, last);
-                            },
-                            Err(_) => ()
-                        }
-                    },
-                    None => ()
+    // legal moves restricted by search and exclude moves of limits, which are checked to be legal by search
+    fn root_moves(&self, position: &Shobu) -> Vec<MoveExtended> {
+        let notation = |moves: &Vec<Move>| -> Vec<String> {
+            moves.iter().filter_map(|mv| position.validate_and_extend(mv).ok()).map(|mv| mv.to_string(position.active_player)).collect()
+        };
+        let search_moves = self.limits.search_moves.as_ref().map(notation);
+        let exclude_moves = notation(&self.limits.exclude_moves);
+        position.get_legal_moves().into_iter().filter(|mv| {
+            let encoded = mv.to_string(position.active_player);
+            search_moves.as_ref().is_none_or(|x| x.contains(&encoded)) && !exclude_moves.contains(&encoded)
+        }).collect()
+    }
+
+    // evals of root moves, None if search was stopped
+    fn search_root(&mut self, position: &mut Shobu, moves: &[MoveExtended], depth: usize, start_time: SystemTime) -> Option<Vec<(usize, f64)>> {
+        let mut evals = Vec::new();
+        for (i, mv) in moves.iter().enumerate() {
+            position.make_move_unsafe(&mv.mv);
+            let eval = -self.negamax(position, depth-1, -INF, INF, position.active_player, start_time);
+            position.undo_move();
+            evals.push((i, eval));
+            if self.should_stop(start_time) 
+            {
+                return None;
+            }
+        }
+        Some(evals)
+    }
+
+    // limits given on ponder hit, until finished iteration replaces infinite ones with them
+    fn handed_limits(&self) -> Option<(&SearchLimits, Option<&TimeManager>)> {
+        if !self.limits.infinite { return None; }
+        self.hit_limits.as_ref()?.get().map(|(limits, time_manager)| (limits, time_manager.as_ref()))
+    }
+
+    fn should_stop(&self, start_time: SystemTime) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        let (limits, time_manager) = self.handed_limits().unwrap_or((&self.limits, self.time_manager.as_ref()));
+        if let Some(stop) = &limits.stop {
+            if stop.load(Ordering::Relaxed) { return true; }
+        }
+        if limits.infinite {
+            return false;
+        }
+        if let Some(nodes) = limits.nodes {
+            if self.nodes.load(Ordering::Relaxed) >= nodes { return true; }
+        }
+        if let Some(time_manager) = time_manager {
+            if start_time.elapsed().map_or(true, |x| x.as_millis() > time_manager.maximum()) { return true; }
+        }
+        match limits.movetime {
+            Some(movetime) => match start_time.elapsed() {
+                Ok(elapsed) => {
+                    elapsed.as_millis() > (movetime as f64 * self.use_time_percentage) as u128 
+                }
+                _ => true
+            },
+            None => false
+        }
+    }
+
+    fn network_in_position(&self, position: &Shobu) -> bool {
+        match (&self.network, &position.nnue) {
+            (Some(network), Some(state)) => Arc::ptr_eq(network, state.network()),
+            _ => false
+        }
+    }
+
+    pub fn eval(&self, position: &Shobu) -> f64 {
+        if let Some(network) = &self.network {
+            return match &position.nnue {
+                Some(state) if self.network_in_position(position) => state.evaluate(),
+                _ => network.evaluate(position)
+            };
+        }
+        self.explain_eval(position).total()
+    }
+
+    // separate terms of handcrafted eval, their sum is eval without network
+    pub fn explain_eval(&self, position: &Shobu) -> EvalBreakdown {
+        let mut breakdown = EvalBreakdown::default();
+        let summaries = [position.move_summary(BLACK), position.move_summary(WHITE)];
+        let threats = [summaries[0].threats(), summaries[1].threats()];
+        let stones = position.pieces.iter().flatten().flatten().filter(|&&tile| tile != NOT_ON_BOARD).count();
+        breakdown.phase = stones as f64 / PHASE_STONES as f64;
+        for (color, player) in [BLACK, WHITE].iter().enumerate() {
+            let sign = *player as f64;
+            for (board_id, piece_list) in position.pieces[color].iter().enumerate() {
+                let mut material = 0;
+                for &tile in piece_list {
+                    if tile == NOT_ON_BOARD { continue; }
+                    breakdown.psts[color][board_id] += sign * self.params.pst_score(color, board_id, tile, breakdown.phase);
+                    material += 1;
                 }
+                breakdown.material[color][board_id] = sign * self.params.material[material];
+                let ejectable = threats[1 - color].ejectable[board_id].count_ones() as usize;
+                breakdown.ejectable[color][board_id] = ejectable;
+                let mut threat = self.params.threat_ejectable * ejectable as f64;
+                if ejectable > 0 && (1..=2).contains(&material) {
+                    threat += self.params.threat_low_material[material - 1];
+                }
+                breakdown.threats[color][board_id] = sign * threat;
+            }
+            breakdown.available_pushes[color] = threats[color].pushes;
+            if position.active_player == *player {
+                breakdown.pushes[color] = sign * self.params.threat_pushes * threats[color].pushes as f64;
+            }
+            // home boards
+            for board_id in [2 * color, 2 * color + 1] {
+                breakdown.passive_directions[color][board_id] = summaries[color].passive_directions(board_id);
+                let directions = summaries[color].move_directions(board_id);
+                breakdown.move_directions[color][board_id] = directions;
+                breakdown.mobility[color][board_id] = sign * self.params.board_mobility_score(directions);
             }
-            None => ()
         }
-        moves
+        breakdown
+    }
+
+    // best move of transposition in orientation of position, if legal
+    fn tt_move(&self, position: &Shobu) -> Option<MoveExtended> {
+        let entry = self.tt.get(position)?;
+        let (color_swap, horizontal_swap) = symmetry::transposition_symmetries(position, &entry)?;
+        position.validate_and_extend(&entry.best_move.to_move().to_symmetric(color_swap, horizontal_swap)).ok()
     }
 
     fn moves_order_tt_lookup(&mut self, position: &mut Shobu, depth: usize) -> Vec<MoveExtended> {
@@ -154,8 +510,8 @@ impl ShobuBot {
             let mut eval = if mv.push_1 || mv.push_2 {-1000.0 + 1.0} else {-1000.0};
             if depth < self.max_depth - 1 {
                 _ = position.make_move_unsafe(&mv.mv);
-                match self.tt.get(&position.get_hash()) {
-                    Some(entry) => eval += entry.eval,
+                match self.tt.get(position) {
+                    Some(entry) => eval += entry.eval(),
                     None => ()
                 }
                 position.undo_move();
@@ -168,11 +524,18 @@ impl ShobuBot {
         moves
     }
     
-    fn get_transposition(&mut self, position: &Shobu, depth: usize) -> Option<&TTEntry> {
-        match self.tt.get(&position.get_hash()) {
+    fn ply(&self, position: &Shobu) -> usize {
+        position.history.len() - self.root_ply
+    }
+
+    // bound and score relative to root of entry deep enough
+    fn get_transposition(&mut self, position: &Shobu, depth: usize) -> Option<(Bound, f64)> {
+        self.stats.tt_probes += 1;
+        match self.tt.get(position) {
             Some(entry) => {
-                if entry.depth >= depth {
-                    return Some(entry)
+                self.stats.tt_hits += 1;
+                if entry.depth as usize >= depth {
+                    return Some((entry.bound, score_from_tt(entry.eval(), self.ply(position))))
                 }
             },
             None => ()
@@ -180,56 +543,111 @@ impl ShobuBot {
         None
     }
 
+    // extends search along pushes ejecting stones to avoid horizon effect
+    fn quiescence(&mut self, position: &mut Shobu, depth: usize, alpha_prev: f64, beta: f64, start_time: SystemTime) -> f64 {
+        if self.should_stop(start_time) {
+            return 0.0;
+        }
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        self.stats.seldepth = usize::max(self.stats.seldepth, self.ply(position));
+        if position.winner != 0 {
+            return (position.winner * position.active_player) as f64 * (WIN_EVAL - self.ply(position) as f64);
+        }
+        // stand pat, side to move is never forced to push
+        let stand_pat = position.active_player as f64 * self.eval(position);
+        if stand_pat >= beta || depth == 0 {
+            return stand_pat;
+        }
+        let mut alpha = f64::max(alpha_prev, stand_pat);
+        let mut best_eval = stand_pat;
+        // every passive partner is searched, as it changes resulting position and replies to it
+        for mv in position.ejecting_move_list().iter() {
+            position.make_move_unsafe(&mv.mv);
+            let eval = -self.quiescence(position, depth - 1, -beta, -alpha, start_time);
+            position.undo_move();
+            best_eval = f64::max(best_eval, eval);
+            alpha = f64::max(alpha, best_eval);
+            if alpha >= beta { break; }
+        }
+        best_eval
+    }
+
     fn negamax(&mut self, position: &mut Shobu, depth: usize, alpha_prev: f64, beta_prev: f64, active_player: i8, start_time: SystemTime) -> f64 {
         // if time is ending, return value wont be used
-        if self.time_is_ending(start_time) {
+        if self.should_stop(start_time) {
             return 0.0;
         }
         let mut alpha = alpha_prev;
         let mut beta = beta_prev;
-        self.negamax_calls += 1;
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        self.stats.seldepth = usize::max(self.stats.seldepth, self.ply(position));
         match self.get_transposition(position, depth) {
-            Some(entry) => {
-                match entry.flag {
-                    EXACT => return entry.eval,
-                    LOWERBOUND => alpha = f64::max(alpha, entry.eval),
-                    UPPERBOUND => beta = f64::min(beta, entry.eval),
-                    _ => ()
+            Some((bound, eval)) => {
+                match bound {
+                    Bound::Exact => {
+                        self.stats.tt_cutoffs += 1;
+                        return eval
+                    },
+                    Bound::Lower => alpha = f64::max(alpha, eval),
+                    Bound::Upper => beta = f64::min(beta, eval)
                 }
                 if alpha >= beta
                 { 
-                    return entry.eval; 
+                    self.stats.tt_cutoffs += 1;
+                    return eval; 
                 }
             },
             None => ()
         }
 
         if position.winner != 0 {
-            return (position.winner * position.active_player) as f64 * (WIN_EVAL + depth as f64);
+            return (position.winner * position.active_player) as f64 * (WIN_EVAL - self.ply(position) as f64);
+        }
+        if let Some(score) = self.tablebases.as_ref().and_then(|x| x.probe(position)) {
+            self.stats.tb_hits += 1;
+            return score.eval(self.ply(position));
         }
         if depth == 0 {
-            return position.active_player as f64 * self.eval(position);
+            return self.quiescence(position, QUIESCENCE_DEPTH, alpha, beta, start_time);
         }
-        let moves = self.moves_ordered(position);
+        let mut moves = StagedMoves::new(self.tt_move(position));
         let mut best_eval: f64 = -INF;
-        let mut best_move = &moves[0].mv;
-        for i in 0..moves.len() {
-            position.make_move_unsafe(&moves[i].mv);
+        let mut best_move = None;
+        let mut searched = 0;
+        while let Some(mv) = moves.next(position) {
+            position.make_move_unsafe(&mv.mv);
             let eval = -self.negamax(position, depth - 1, -beta, -alpha, -active_player, start_time);
             position.undo_move();
+            searched += 1;
             if eval > best_eval {
                 best_eval = eval;
-                best_move = &moves[i].mv;
+                best_move = Some(mv.mv);
             }
             alpha = f64::max(alpha, best_eval);
-            if alpha >= beta { break; };
+            if alpha >= beta {
+                self.stats.beta_cutoffs += 1;
+                if searched == 1 { self.stats.first_move_cutoffs += 1; }
+                break;
+            };
+        }
+        // partial result of stopped search is neither used nor stored
+        if self.should_stop(start_time) {
+            return 0.0;
         }
+        // player without legal move loses
+        let Some(best_move) = best_move else { return -(WIN_EVAL - self.ply(position) as f64); };
 
-        let flag = if best_eval <= alpha_prev { UPPERBOUND }
-            else if best_eval >= beta { LOWERBOUND }
-            else { EXACT };
-        let new_entry = TTEntry::new(position.get_symmetry_hash(false, false), best_eval, flag, depth, best_move.deep_copy());
-        self.tt.insert(position.get_hash(), new_entry);
+        let bound = if best_eval <= alpha_prev { Bound::Upper }
+            else if best_eval >= beta { Bound::Lower }
+            else { Bound::Exact };
+        let new_entry = TTEntry::new(position.get_symmetry_hash(false, false), score_to_tt(best_eval, self.ply(position)), bound, depth, best_move);
+        self.tt.insert(position, &new_entry);
         best_eval
     }
+}
+
+impl Drop for ShobuBot {
+    fn drop(&mut self) {
+        self.stop_pondering();
+    }
 }
\ No newline at end of file
@@ -20,8 +20,39 @@ pub const PSTS: [[f64; 36]; 2] = [
 pub const INF: f64 = 1_000_000_000.0;
 pub const WIN_EVAL: f64 = 1_000_000.0;
 pub const MAX_DEPTH: usize = 36;
+// win scores are WIN_EVAL lowered by distance from root
+pub const MAX_PLY: usize = 128;
+pub const QUIESCENCE_DEPTH: usize = 2;
 pub const TT_SIZE: usize = 1_048_577;
 pub const MATERIAL: [f64; 5] = [-WIN_EVAL, 0.0, 15.0, 30.0, 40.0];
+pub const MOBILITY_LOW: usize = 7;
+pub const MOBILITY_HIGH: usize = 13;
+pub const MOBILITY_LOW_PENALTY: f64 = -10.0;
+pub const MOBILITY_WEIGHT: f64 = 0.7;
+pub const MOBILITY_HIGH_BASE: f64 = 8.4;
+pub const MOBILITY_HIGH_WEIGHT: f64 = 0.5;
+// per stone opponent can eject, for attacked board with one or two stones and per push of side to move
+pub const THREAT_EJECTABLE: f64 = -2.0;
+pub const THREAT_LOW_MATERIAL: [f64; 2] = [-15.0, -5.0];
+pub const THREAT_PUSHES: f64 = 0.5;
+// stones on all boards at game start, phase of piece square tables is stones left divided by it
+pub const PHASE_STONES: usize = 32;
+pub const PST_TAPERED: bool = false;
 pub const MS_PER_MOVE: u128 = 2000;
 pub const USE_TIME_PERCENTAGE: f64 = 0.97;
+pub const THREADS: usize = 1;
 //pub const MATERIAL: [f64; 5] = [-WIN_EVAL, 0.0, 10.0, 21.0, 28.0];
+
+// time management
+pub const MOVE_OVERHEAD: u128 = 50;
+pub const MOVES_TO_GO: u128 = 25;
+pub const MAX_TIME_PERCENTAGE: u128 = 30;
+pub const MAX_TIME_FACTOR: u128 = 4;
+pub const NEXT_ITERATION_PERCENTAGE: f64 = 0.6;
+pub const BEST_MOVE_CHANGE_FACTOR: f64 = 1.5;
+pub const BEST_MOVE_STABLE_FACTOR: f64 = 0.9;
+pub const SCORE_DROP_MARGIN: f64 = 5.0;
+pub const SCORE_DROP_FACTOR: f64 = 1.3;
+pub const MAX_STABILITY_FACTOR: f64 = 3.0;
+pub const DOMINANCE_MARGIN: f64 = 30.0;
+pub const DOMINANCE_FACTOR: f64 = 0.3;
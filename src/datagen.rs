@@ -0,0 +1,57 @@
+use rustc_hash::FxHashSet;
+use crate::bot::ShobuBot;
+use crate::bot_constants::{MAX_PLY, WIN_EVAL};
+use crate::search_limits::SearchLimits;
+use crate::shobu::{Shobu, WHITE};
+
+pub struct DatagenSettings {
+    pub limits: SearchLimits,
+    // game is drawn after this many moves
+    pub max_moves: usize
+}
+
+// self-play positions as "position;result;score;bestmove" lines readable by tuner
+// result and score are from white perspective
+pub struct Datagen {
+    settings: DatagenSettings,
+    // symmetry invariant hashes of written positions
+    seen: FxHashSet<u64>
+}
+
+impl Datagen {
+    pub fn new(settings: DatagenSettings) -> Self {
+        Datagen { settings, seen: FxHashSet::default() }
+    }
+
+    // positions of records already written, e.g. to file appended to
+    pub fn add_seen(&mut self, records: &str) {
+        for line in records.lines().filter(|x| !x.trim().is_empty()) {
+            let position = line.split(';').next().unwrap();
+            self.seen.insert(Shobu::from_string(position).get_hash());
+        }
+    }
+
+    // positions with ejecting push available, with won score or from book are skipped, as eval can not judge them
+    pub fn play_game(&mut self, bot: &mut ShobuBot, opening: &str) -> Vec<String> {
+        let mut game = Shobu::from_string(opening);
+        let mut records = Vec::new();
+        for _ in 0..self.settings.max_moves {
+            if game.winner != 0 || game.count_legal_moves() == 0 { break; }
+            let Ok(result) = bot.search(&mut game, &self.settings.limits) else { break; };
+            let best_move = game.validate_and_extend(&result.best_move).unwrap().to_string(game.active_player);
+            // tactical position, stone can be pushed off whether or not best move does it
+            let push_pending = !game.get_ejecting_moves().is_empty();
+            let quiet = result.depth > 0 && !push_pending && result.eval.abs() < WIN_EVAL - MAX_PLY as f64;
+            if quiet && self.seen.insert(game.get_hash()) {
+                records.push((game.to_string(), result.eval * game.active_player as f64, best_move));
+            }
+            game.make_move_unsafe(&result.best_move);
+        }
+        let result = match game.winner {
+            0 if game.count_legal_moves() == 0 => if game.active_player == WHITE { 0.0 } else { 1.0 },
+            0 => 0.5,
+            winner => if winner == WHITE { 1.0 } else { 0.0 }
+        };
+        records.into_iter().map(|(position, score, best_move)| format!("{};{};{:.2};{}", position, result, score, best_move)).collect()
+    }
+}
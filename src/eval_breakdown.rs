@@ -0,0 +1,57 @@
+use std::fmt;
+
+// terms of handcrafted eval from white perspective indexed by colour (black, white) and board
+// mobility is counted only on home boards, 0 and 1 for black, 2 and 3 for white
+// threats are penalties for own stones opponent can eject, pushes are counted only for side to move
+#[derive(Default)]
+pub struct EvalBreakdown {
+    pub material: [[f64; 4]; 2],
+    pub psts: [[f64; 4]; 2],
+    pub mobility: [[f64; 4]; 2],
+    pub passive_directions: [[usize; 4]; 2],
+    // passive directions with matching aggressive move, mobility is scored by them
+    pub move_directions: [[usize; 4]; 2],
+    pub threats: [[f64; 4]; 2],
+    pub ejectable: [[usize; 4]; 2],
+    pub pushes: [f64; 2],
+    pub available_pushes: [usize; 2],
+    // weight of midgame piece square tables
+    pub phase: f64
+}
+
+impl EvalBreakdown {
+    pub fn total(&self) -> f64 {
+        let mut total = 0.0;
+        for color in 0..2 {
+            for board in 0..4 {
+                total += self.material[color][board] + self.psts[color][board] + self.mobility[color][board] + self.threats[color][board];
+            }
+            total += self.pushes[color];
+        }
+        total
+    }
+}
+
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<20}{:>10}{:>10}{:>10}{:>10}", "board", 0, 1, 2, 3)?;
+        let mut row = |name: &str, values: [String; 4]| {
+            writeln!(f, "{:<20}{}", name, values.map(|x| format!("{:>10}", x)).concat())
+        };
+        // negative zero of black terms is printed without sign
+        let scores = |values: &[f64; 4]| values.map(|x| format!("{:.2}", x + 0.0));
+        for (color, name) in ["black", "white"].iter().enumerate() {
+            row(&format!("{} material", name), scores(&self.material[color]))?;
+            row(&format!("{} pst", name), scores(&self.psts[color]))?;
+            row(&format!("{} mobility", name), scores(&self.mobility[color]))?;
+            row(&format!("{} directions", name), self.passive_directions[color].map(|x| x.to_string()))?;
+            row(&format!("{} moves", name), self.move_directions[color].map(|x| x.to_string()))?;
+            row(&format!("{} threats", name), scores(&self.threats[color]))?;
+            row(&format!("{} ejectable", name), self.ejectable[color].map(|x| x.to_string()))?;
+            let pushes = format!("{:.2}", self.pushes[color] + 0.0);
+            row(&format!("{} pushes", name), [pushes, self.available_pushes[color].to_string(), String::new(), String::new()])?;
+        }
+        writeln!(f, "{:<20}{:>10.2}", "phase", self.phase)?;
+        write!(f, "{:<20}{:>10.2}", "total", self.total())
+    }
+}
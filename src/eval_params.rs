@@ -0,0 +1,176 @@
+use std::fmt;
+use std::fs;
+use crate::bot_constants::*;
+use crate::shobu::TILES;
+
+const PST_ROLES: [&str; 4] = ["home_dark", "home_light", "opponent_dark", "opponent_light"];
+
+// weights of evaluation terms, stored in text file as "name value value ..." lines
+#[derive(Clone)]
+pub struct EvalParams {
+    // indexed by colour, board role and tile, endgame tables are used only when tapered
+    pub psts: [[[f64; 36]; 4]; 2],
+    pub psts_endgame: [[[f64; 36]; 4]; 2],
+    pub pst_tapered: bool,
+    pub material: [f64; 5],
+    pub mobility_low: usize,
+    pub mobility_high: usize,
+    pub mobility_low_penalty: f64,
+    pub mobility_weight: f64,
+    pub mobility_high_base: f64,
+    pub mobility_high_weight: f64,
+    pub threat_ejectable: f64,
+    // board with one or two own stones where opponent can eject one
+    pub threat_low_material: [f64; 2],
+    pub threat_pushes: f64
+}
+
+fn parse_values(values: &[&str]) -> Result<Vec<f64>, String> {
+    values.iter().map(|x| x.parse().map_err(|_| format!("Invalid value {}!", x))).collect()
+}
+
+fn expect_len(name: &str, values: &[f64], len: usize) -> Result<(), String> {
+    if values.len() != len { return Err(format!("Expected {} values for {}!", len, name)); }
+    Ok(())
+}
+
+impl EvalParams {
+    pub fn new() -> Self {
+        EvalParams {
+            psts: PSTS.map(|pst| [pst; 4]),
+            psts_endgame: PSTS.map(|pst| [pst; 4]),
+            pst_tapered: PST_TAPERED,
+            material: MATERIAL,
+            mobility_low: MOBILITY_LOW,
+            mobility_high: MOBILITY_HIGH,
+            mobility_low_penalty: MOBILITY_LOW_PENALTY,
+            mobility_weight: MOBILITY_WEIGHT,
+            mobility_high_base: MOBILITY_HIGH_BASE,
+            mobility_high_weight: MOBILITY_HIGH_WEIGHT,
+            threat_ejectable: THREAT_EJECTABLE,
+            threat_low_material: THREAT_LOW_MATERIAL,
+            threat_pushes: THREAT_PUSHES
+        }
+    }
+
+    // home dark, home light, opponent dark, opponent light, boards 0 and 2 are dark
+    pub fn pst_role(color: usize, board_id: usize) -> usize {
+        let opponent = if board_id / 2 == color {0} else {2};
+        opponent + board_id % 2
+    }
+
+    // phase is 1.0 with all stones on boards and 0.0 without stones
+    pub fn pst_score(&self, color: usize, board_id: usize, tile: usize, phase: f64) -> f64 {
+        let role = Self::pst_role(color, board_id);
+        let midgame = self.psts[color][role][tile];
+        if !self.pst_tapered { return midgame; }
+        phase * midgame + (1.0 - phase) * self.psts_endgame[color][role][tile]
+    }
+
+    // piecewise linear in number of directions and distances with passive and aggressive move
+    pub fn board_mobility_score(&self, mobility: usize) -> f64 {
+        if mobility < self.mobility_low {
+            self.mobility_low_penalty + self.mobility_weight * mobility as f64
+        } else if mobility < self.mobility_high {
+            self.mobility_weight * mobility as f64
+        } else {
+            self.mobility_high_base + self.mobility_high_weight * (mobility as f64 - (self.mobility_high - 1) as f64)
+        }
+    }
+
+    // missing parameters keep default values
+    pub fn from_string(string: &str) -> Result<Self, String> {
+        let mut params = EvalParams::new();
+        for line in string.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.is_empty() || parts[0].starts_with('#') { continue; }
+            let name = parts[0];
+            let values = parse_values(&parts[1..])?;
+            match name {
+                // single table for all roles and phases
+                "pst_black" | "pst_white" => {
+                    expect_len(name, &values, TILES.len())?;
+                    let color = if name == "pst_black" {0} else {1};
+                    for psts in [&mut params.psts, &mut params.psts_endgame] {
+                        for pst in psts[color].iter_mut() {
+                            for (tile, value) in TILES.iter().zip(&values) {
+                                pst[*tile] = *value;
+                            }
+                        }
+                    }
+                },
+                _ if name.starts_with("pst_") && name != "pst_tapered" => {
+                    let (psts, table) = match name.strip_prefix("pst_endgame_") {
+                        Some(table) => (&mut params.psts_endgame, table),
+                        None => (&mut params.psts, &name[4..])
+                    };
+                    let (color, role) = table.split_once('_').ok_or(format!("Unknown parameter {}!", name))?;
+                    let color = ["black", "white"].iter().position(|x| *x == color).ok_or(format!("Unknown parameter {}!", name))?;
+                    let role = PST_ROLES.iter().position(|x| *x == role).ok_or(format!("Unknown parameter {}!", name))?;
+                    expect_len(name, &values, TILES.len())?;
+                    for (tile, value) in TILES.iter().zip(values) {
+                        psts[color][role][*tile] = value;
+                    }
+                },
+                "material" => {
+                    expect_len(name, &values, params.material.len())?;
+                    params.material.copy_from_slice(&values);
+                },
+                "threat_low_material" => {
+                    expect_len(name, &values, params.threat_low_material.len())?;
+                    params.threat_low_material.copy_from_slice(&values);
+                },
+                _ => {
+                    expect_len(name, &values, 1)?;
+                    let value = values[0];
+                    match name {
+                        "mobility_low" => params.mobility_low = value as usize,
+                        "mobility_high" => params.mobility_high = value as usize,
+                        "mobility_low_penalty" => params.mobility_low_penalty = value,
+                        "mobility_weight" => params.mobility_weight = value,
+                        "mobility_high_base" => params.mobility_high_base = value,
+                        "mobility_high_weight" => params.mobility_high_weight = value,
+                        "threat_ejectable" => params.threat_ejectable = value,
+                        "threat_pushes" => params.threat_pushes = value,
+                        "pst_tapered" => params.pst_tapered = value != 0.0,
+                        _ => return Err(format!("Unknown parameter {}!", name))
+                    }
+                }
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let string = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_string(&string)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |values: &[f64]| values.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ");
+        writeln!(f, "pst_tapered {}", self.pst_tapered as usize)?;
+        for (prefix, psts) in [("pst", &self.psts), ("pst_endgame", &self.psts_endgame)] {
+            for (color, color_name) in ["black", "white"].iter().enumerate() {
+                for (role, role_name) in PST_ROLES.iter().enumerate() {
+                    writeln!(f, "{}_{}_{} {}", prefix, color_name, role_name, join(&TILES.map(|tile| psts[color][role][tile])))?;
+                }
+            }
+        }
+        writeln!(f, "material {}", join(&self.material))?;
+        writeln!(f, "mobility_low {}", self.mobility_low)?;
+        writeln!(f, "mobility_high {}", self.mobility_high)?;
+        writeln!(f, "mobility_low_penalty {}", self.mobility_low_penalty)?;
+        writeln!(f, "mobility_weight {}", self.mobility_weight)?;
+        writeln!(f, "mobility_high_base {}", self.mobility_high_base)?;
+        writeln!(f, "mobility_high_weight {}", self.mobility_high_weight)?;
+        writeln!(f, "threat_ejectable {}", self.threat_ejectable)?;
+        writeln!(f, "threat_low_material {}", join(&self.threat_low_material))?;
+        writeln!(f, "threat_pushes {}", self.threat_pushes)
+    }
+}
@@ -1,18 +1,315 @@
 mod shobu;
 mod tests;
 mod shobu_move;
+mod move_list;
+mod packed_move;
+mod movement;
 mod bot;
 mod bot_constants;
 mod utils;
 mod tt_entry;
+mod transposition_table;
+mod search_limits;
+mod search_result;
+mod search_stats;
+mod eval_params;
+mod eval_breakdown;
+mod tuner;
+mod match_runner;
+mod book;
+mod mcts;
+mod player;
+mod nnue;
+mod datagen;
+mod tablebase;
+mod time_manager;
 pub mod symmetry;
 
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use crate::book::OpeningBook;
 use crate::bot::ShobuBot;
+use crate::datagen::{Datagen, DatagenSettings};
+use crate::bot_constants::MS_PER_MOVE;
+use crate::eval_params::EvalParams;
+use crate::mcts::{MctsBot, Playout};
+use crate::nnue::Network;
+use crate::match_runner::{EngineConfig, MatchRunner, MatchSettings};
+use crate::search_limits::SearchLimits;
+use crate::search_result::Score;
 use crate::shobu::Shobu;
+use crate::tablebase::{Signature, Tablebases};
+use crate::time_manager::GameClock;
+use crate::tuner::Tuner;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
     let mut bot = ShobuBot::new();
-    bot.play_game();
+    if let Some(threads) = utils::arg_value(&args, "--threads") {
+        bot.set_threads(threads.parse().expect("Invalid thread count!"));
+    }
+    bot.set_ponder(args.iter().any(|x| x == "--ponder"));
+    if let Some(path) = utils::arg_value(&args, "--params") {
+        bot.set_params(EvalParams::load(&path).unwrap());
+    }
+    if let Some(path) = utils::arg_value(&args, "--book") {
+        bot.set_book(Some(Arc::new(OpeningBook::load(&path).unwrap())));
+    }
+    if let Some(path) = utils::arg_value(&args, "--nnue") {
+        bot.set_network(Some(Arc::new(Network::load(&path).unwrap())));
+    }
+    if let Some(path) = utils::arg_value(&args, "--tablebases") {
+        bot.set_tablebases(Some(Arc::new(Tablebases::load_dir(&path).unwrap())));
+    }
+    match args.get(1).map(|x| x.as_str()) {
+        Some("search") => search(&mut bot, &args),
+        // usage: save-params <file>, writes parameters used by bot
+        Some("save-params") => bot.params().save(args.get(2).expect("File not given!")).unwrap(),
+        Some("tune") => tune(&bot, &args),
+        Some("match") => run_match(&bot, &args),
+        Some("book") => book(&mut bot, &args),
+        Some("mcts") => mcts(&bot, &args),
+        Some("play") => play(&bot, &args),
+        Some("datagen") => datagen(&mut bot, &args),
+        Some("tablebase") => generate_tablebase(&args),
+        // usage: explain "<position>", prints terms of handcrafted eval from white perspective
+        Some("explain") => {
+            let game = Shobu::from_string(args.get(2).expect("Position not given!"));
+            println!("{}", bot.explain_eval(&game));
+            println!("eval {:+.2}", bot.eval(&game));
+        },
+        // usage: nnue-init <file> [--layers n1,n2,...] [--seed n], writes randomly initialized network
+        Some("nnue-init") => {
+            let layers: Vec<usize> = utils::arg_value(&args, "--layers").unwrap_or("32,16,1".to_string())
+                .split(',').map(|x| x.parse().expect("Invalid layer size!")).collect();
+            let seed = utils::arg_value(&args, "--seed").map_or(0, |x| x.parse().expect("Invalid seed!"));
+            Network::random(&layers, seed).unwrap().save(args.get(2).expect("File not given!")).unwrap();
+        },
+        _ => bot.play_game()
+    }
 //     let mut game = Shobu::new();
 //     let mv = bot.choose_move(&mut game);
-}
\ No newline at end of file
+}
+
+fn parse_limits(args: &[String], active_player: i8) -> Result<SearchLimits, String> {
+    let mut limits = parse_stop_limits(args);
+    if let Some(search_moves) = utils::arg_value(args, "--searchmoves") {
+        limits.search_moves = Some(search_limits::parse_moves(&search_moves, active_player)?);
+    }
+    if let Some(exclude_moves) = utils::arg_value(args, "--excludemoves") {
+        limits.exclude_moves = search_limits::parse_moves(&exclude_moves, active_player)?;
+    }
+    Ok(limits)
+}
+
+fn exit_with_error(error: String) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+fn parse_stop_limits(args: &[String]) -> SearchLimits {
+    if args.iter().any(|x| x == "--infinite") {
+        // search until enter is pressed
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_input = Arc::clone(&stop);
+        thread::spawn(move || {
+            let _ = utils::input(&io::stdin());
+            stop_input.store(true, Ordering::Relaxed);
+        });
+        return SearchLimits::infinite(stop);
+    }
+    let depth = utils::arg_value(args, "--depth").map(|x| x.parse().expect("Invalid depth!"));
+    let nodes = utils::arg_value(args, "--nodes").map(|x| x.parse().expect("Invalid node count!"));
+    let movetime = utils::arg_value(args, "--movetime").map(|x| x.parse().expect("Invalid move time!"));
+    let clock = utils::arg_value(args, "--clock").map(|x| GameClock::from_string(&x).expect("Invalid clock!"));
+    let mut limits = match (depth, nodes, clock) {
+        (Some(depth), _, _) => SearchLimits::depth(depth),
+        (None, Some(nodes), _) => SearchLimits::nodes(nodes),
+        (None, None, Some(clock)) => SearchLimits::clock(clock),
+        (None, None, None) => SearchLimits::movetime(movetime.unwrap_or(MS_PER_MOVE))
+    };
+    limits.clock = clock;
+    limits.nodes = nodes;
+    if movetime.is_some() { limits.movetime = movetime; }
+    limits
+}
+
+// usage: search "<position>" [--depth n] [--nodes n] [--movetime ms] [--clock "btime wtime binc winc"] [--infinite] [--multipv n]
+//     [--searchmoves m1,m2] [--excludemoves m1,m2]
+fn search(bot: &mut ShobuBot, args: &[String]) {
+    let mut game = Shobu::from_string(args.get(2).expect("Position not given!"));
+    let limits = parse_limits(args, game.active_player).unwrap_or_else(|x| exit_with_error(x));
+    if let Some(multipv) = utils::arg_value(args, "--multipv") {
+        let lines = bot.analyse(&mut game, &limits, multipv.parse().expect("Invalid multipv!")).unwrap_or_else(|x| exit_with_error(x));
+        for (i, line) in lines.iter().enumerate() {
+            println!("multipv {} depth {} score {} move {} pv {}", i + 1, line.depth, line.score, line.mv, line.pv.join(" "));
+        }
+        return;
+    }
+    let result = bot.search(&mut game, &limits).unwrap_or_else(|x| exit_with_error(x));
+    for stats in &result.iterations {
+        println!("info {}", stats);
+    }
+    if limits.search_moves.is_some() {
+        for (mv, eval) in &result.root_moves {
+            println!("move {} score {}", game.validate_and_extend(mv).unwrap().to_string(game.active_player), Score::from_eval(*eval));
+        }
+    }
+    println!("bestmove {} score {} depth {} pv {}{}",
+        game.validate_and_extend(&result.best_move).unwrap().to_string(game.active_player),
+        result.score(), result.depth, result.pv_to_string(&game).join(" "),
+        if result.ponder_hit { " ponderhit" } else { "" });
+}
+
+// usage: tune <positions file> <output file> [--iterations n] [--step x]
+fn tune(bot: &ShobuBot, args: &[String]) {
+    let mut tuner = Tuner::load(args.get(2).expect("Positions file not given!"), bot.params().clone()).unwrap();
+    let iterations = utils::arg_value(args, "--iterations").map_or(100, |x| x.parse().expect("Invalid iterations!"));
+    let step = utils::arg_value(args, "--step").map_or(0.1, |x| x.parse().expect("Invalid step!"));
+    println!("scaling {}", tuner.fit_scaling());
+    println!("error before {}", tuner.error());
+    let params = tuner.tune(iterations, step);
+    println!("error after {}", tuner.error());
+    params.save(args.get(3).expect("Output file not given!")).unwrap();
+}
+
+// usage: match [--params-a file] [--params-b file] [--player-a kind] [--player-b kind] [--games n] [--openings file] [--record file] [--sprt "elo0 elo1"]
+//     [--max-moves n] [--depth n] [--nodes n] [--movetime ms]
+fn run_match(bot: &ShobuBot, args: &[String]) {
+    let config = |name: &str| {
+        let path = utils::arg_value(args, name);
+        EngineConfig {
+            name: path.clone().unwrap_or("default".to_string()),
+            player: utils::arg_value(args, &name.replace("params", "player")).unwrap_or("minimax".to_string()),
+            params: path.map_or(bot.params().clone(), |x| EvalParams::load(&x).unwrap()),
+            threads: utils::arg_value(args, "--threads").map_or(1, |x| x.parse().expect("Invalid thread count!"))
+        }
+    };
+    let configs = [config("--params-a"), config("--params-b")];
+    let names = [configs[0].name.clone(), configs[1].name.clone()];
+    let openings = match utils::arg_value(args, "--openings") {
+        Some(path) => std::fs::read_to_string(path).unwrap().lines().filter(|x| !x.trim().is_empty()).map(|x| x.trim().to_string()).collect(),
+        None => match_runner::random_openings(50, 8, 0)
+    };
+    let sprt = utils::arg_value(args, "--sprt").map(|x| {
+        let elos: Vec<f64> = x.split_whitespace().map(|y| y.parse().expect("Invalid sprt bounds!")).collect();
+        (elos[0], elos[1], 0.05, 0.05)
+    });
+    let settings = MatchSettings {
+        games: utils::arg_value(args, "--games").map_or(100, |x| x.parse().expect("Invalid game count!")),
+        limits: parse_stop_limits(args),
+        max_moves: utils::arg_value(args, "--max-moves").map_or(200, |x| x.parse().expect("Invalid move count!")),
+        openings,
+        sprt
+    };
+    let mut runner = MatchRunner::new(configs, settings).unwrap();
+    let result = runner.run();
+    let (elo, margin) = result.elo();
+    println!("{} vs {}: wins {} draws {} losses {}", names[0], names[1], result.wins, result.draws, result.losses);
+    println!("elo {:+.1} +/- {:.1}", elo, margin);
+    if let Some((elo0, elo1, alpha, beta)) = sprt {
+        println!("sprt llr {:.2} verdict {:?}", result.llr(elo0, elo1), result.sprt(elo0, elo1, alpha, beta));
+    }
+    if let Some(path) = utils::arg_value(args, "--record") {
+        result.save_games(&path, [&names[0], &names[1]]).unwrap();
+    }
+}
+
+// usage: book build <output file> [--plies n] [--width n] [--depth n] [--nodes n] [--movetime ms]
+//     book import <game records> <output file> [--plies n]
+fn book(bot: &mut ShobuBot, args: &[String]) {
+    let plies = utils::arg_value(args, "--plies").map_or(6, |x| x.parse().expect("Invalid plies!"));
+    let mut book = OpeningBook::new();
+    let output = match args.get(2).map(|x| x.as_str()) {
+        Some("build") => {
+            let width = utils::arg_value(args, "--width").map_or(2, |x| x.parse().expect("Invalid width!"));
+            book.build(bot, &mut Shobu::new(), plies, width, &parse_stop_limits(args));
+            args.get(3)
+        }
+        Some("import") => {
+            let records = std::fs::read_to_string(args.get(3).expect("Game records not given!")).unwrap();
+            book.add_games(&records, plies).unwrap();
+            args.get(4)
+        }
+        _ => panic!("Unknown book command!")
+    };
+    book.save(output.expect("Output file not given!")).unwrap();
+    println!("book positions {}", book.position_count());
+}
+
+// usage: mcts "<position>" [--exploration c] [--playout random|eval] [--seed n] [--nodes iterations] [--movetime ms] [--clock "btime wtime binc winc"]
+fn mcts(bot: &ShobuBot, args: &[String]) {
+    let mut game = Shobu::from_string(args.get(2).expect("Position not given!"));
+    let exploration = utils::arg_value(args, "--exploration").map_or(mcts::EXPLORATION, |x| x.parse().expect("Invalid exploration!"));
+    let playout = match utils::arg_value(args, "--playout").as_deref() {
+        Some("eval") => Playout::Eval,
+        Some("random") | None => Playout::Random,
+        Some(x) => panic!("Unknown playout {}!", x)
+    };
+    let mut mcts = MctsBot::new(exploration, playout);
+    mcts.set_params(bot.params().clone());
+    if let Some(seed) = utils::arg_value(args, "--seed") {
+        mcts.set_seed(seed.parse().expect("Invalid seed!"));
+    }
+    let result = mcts.search(&mut game, &parse_stop_limits(args));
+    println!("bestmove {} visits {} winrate {:.3} iterations {}",
+        game.validate_and_extend(&result.best_move).unwrap().to_string(game.active_player), result.visits, result.win_rate, result.iterations);
+}
+
+// usage: play [--black kind] [--white kind] [--depth n] [--nodes n] [--movetime ms], kinds are minimax, mcts, greedy, random and human
+fn play(bot: &ShobuBot, args: &[String]) {
+    let config = |color: &str, default: &str| EngineConfig {
+        name: color.to_string(),
+        player: utils::arg_value(args, &format!("--{}", color)).unwrap_or(default.to_string()),
+        params: bot.params().clone(),
+        threads: utils::arg_value(args, "--threads").map_or(1, |x| x.parse().expect("Invalid thread count!"))
+    };
+    let settings = MatchSettings {
+        games: 1,
+        limits: parse_stop_limits(args),
+        max_moves: usize::MAX,
+        openings: vec![Shobu::new().to_string()],
+        sprt: None
+    };
+    let result = MatchRunner::new([config("black", "human"), config("white", "minimax")], settings).unwrap().run();
+    println!("{}", result.games[0].moves.join(" "));
+}
+
+// usage: datagen <output file> [--games n] [--random-plies n] [--seed n] [--max-moves n] [--depth n] [--nodes n] [--movetime ms]
+// positions of every finished game are appended to output, positions already in it are not written again
+fn datagen(bot: &mut ShobuBot, args: &[String]) {
+    let path = args.get(2).expect("Output file not given!");
+    let games = utils::arg_value(args, "--games").map_or(100, |x| x.parse().expect("Invalid game count!"));
+    let random_plies = utils::arg_value(args, "--random-plies").map_or(8, |x| x.parse().expect("Invalid plies!"));
+    let seed = utils::arg_value(args, "--seed").map_or(0, |x| x.parse().expect("Invalid seed!"));
+    let mut datagen = Datagen::new(DatagenSettings {
+        limits: parse_stop_limits(args),
+        max_moves: utils::arg_value(args, "--max-moves").map_or(200, |x| x.parse().expect("Invalid move count!"))
+    });
+    if let Ok(records) = std::fs::read_to_string(path) {
+        datagen.add_seen(&records);
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+    let mut total = 0;
+    for (i, opening) in match_runner::random_openings(games, random_plies, seed).iter().enumerate() {
+        let records = datagen.play_game(bot, opening);
+        total += records.len();
+        for record in records {
+            writeln!(file, "{}", record).unwrap();
+        }
+        println!("game {} positions {}", i + 1, total);
+    }
+}
+
+// usage: tablebase <signature> <directory>, signature lists attacker and defender stones on home board of attacker
+// and on board paired with it, e.g. a2d1.a1d1, tables of smaller signatures are generated first, ones in directory are reused
+fn generate_tablebase(args: &[String]) {
+    let signature = Signature::from_string(args.get(2).expect("Signature not given!")).unwrap();
+    let directory = args.get(3).expect("Directory not given!");
+    let mut tablebases = Tablebases::load_dir(directory).unwrap_or(Tablebases::new());
+    println!("positions {}", signature.size());
+    tablebases.generate(signature);
+    tablebases.save_dir(directory).unwrap();
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::time::Instant;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::eval_params::EvalParams;
+use crate::player::{self, Player};
+use crate::search_limits::SearchLimits;
+use crate::shobu::{Shobu, BLACK, WHITE};
+
+const MOVE_TIME_MARGIN: u128 = 100;
+
+pub struct EngineConfig {
+    pub name: String,
+    // kind accepted by player::create_player
+    pub player: String,
+    pub params: EvalParams,
+    pub threads: usize
+}
+
+pub struct MatchSettings {
+    pub games: usize,
+    pub limits: SearchLimits,
+    // game is drawn after this many moves
+    pub max_moves: usize,
+    pub openings: Vec<String>,
+    // elo0, elo1, alpha, beta
+    pub sprt: Option<(f64, f64, f64, f64)>
+}
+
+pub struct GameRecord {
+    pub opening: String,
+    // index of engine playing black
+    pub black: usize,
+    pub moves: Vec<String>,
+    pub winner: i8
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SprtVerdict {
+    AcceptH0,
+    AcceptH1,
+    Continue
+}
+
+// results from perspective of first engine
+pub struct MatchResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub games: Vec<GameRecord>
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + f64::powf(10.0, -elo / 400.0))
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * f64::log10(1.0 / score - 1.0)
+}
+
+// positions reached by random moves from starting position
+pub fn random_openings(count: usize, plies: usize, seed: u64) -> Vec<String> {
+    let mut rand = StdRng::seed_from_u64(seed);
+    let mut openings = Vec::new();
+    while openings.len() < count {
+        let mut game = Shobu::new();
+        for _ in 0..plies {
+            let moves = game.get_legal_moves();
+            game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+            if game.winner != 0 { break; }
+        }
+        if game.winner == 0 { openings.push(game.to_string()); }
+    }
+    openings
+}
+
+impl MatchResult {
+    pub fn new() -> Self {
+        MatchResult { wins: 0, draws: 0, losses: 0, games: Vec::new() }
+    }
+
+    pub fn total(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.total() as f64
+    }
+
+    // variance of single game score
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2))
+            / self.total() as f64
+    }
+
+    // elo difference with 95% error margin
+    pub fn elo(&self) -> (f64, f64) {
+        if self.total() == 0 { return (0.0, 0.0); }
+        let score = self.score();
+        let deviation = f64::sqrt(self.variance() / self.total() as f64);
+        let clamp = |x: f64| x.clamp(1e-6, 1.0 - 1e-6);
+        let lower = elo_from_score(clamp(score - 1.96 * deviation));
+        let upper = elo_from_score(clamp(score + 1.96 * deviation));
+        (elo_from_score(clamp(score)), (upper - lower) / 2.0)
+    }
+
+    // log likelihood ratio of elo1 against elo0, normal approximation of game scores
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.variance();
+        if self.total() == 0 || variance == 0.0 { return 0.0; }
+        let (score0, score1) = (expected_score(elo0), expected_score(elo1));
+        (score1 - score0) * (2.0 * self.score() - score0 - score1) * self.total() as f64 / (2.0 * variance)
+    }
+
+    pub fn sprt(&self, elo0: f64, elo1: f64, alpha: f64, beta: f64) -> SprtVerdict {
+        let llr = self.llr(elo0, elo1);
+        if llr >= f64::ln((1.0 - beta) / alpha) {
+            SprtVerdict::AcceptH1
+        } else if llr <= f64::ln(beta / (1.0 - alpha)) {
+            SprtVerdict::AcceptH0
+        } else {
+            SprtVerdict::Continue
+        }
+    }
+
+    // one line per game "opening;black;white;winner;moves"
+    pub fn save_games(&self, path: &str, names: [&str; 2]) -> Result<(), String> {
+        let lines: Vec<String> = self.games.iter().map(|game| {
+            let result = match game.winner { BLACK => "b", WHITE => "w", _ => "d" };
+            format!("{};{};{};{};{}", game.opening, names[game.black], names[1 - game.black], result, game.moves.join(" "))
+        }).collect();
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| e.to_string())
+    }
+}
+
+pub struct MatchRunner {
+    engines: [Box<dyn Player>; 2],
+    settings: MatchSettings
+}
+
+impl MatchRunner {
+    pub fn new(configs: [EngineConfig; 2], settings: MatchSettings) -> Result<Self, String> {
+        let [first, second] = configs;
+        let engines = [
+            player::create_player(&first.player, first.params, first.threads)?,
+            player::create_player(&second.player, second.params, second.threads)?
+        ];
+        Ok(Self::with_players(engines, settings))
+    }
+
+    pub fn with_players(engines: [Box<dyn Player>; 2], settings: MatchSettings) -> Self {
+        MatchRunner { engines, settings }
+    }
+
+    // plays every opening with swapped colours until game count or sprt verdict is reached
+    pub fn run(&mut self) -> MatchResult {
+        let mut result = MatchResult::new();
+        'openings: for opening in self.settings.openings.clone().iter().cycle() {
+            for black in [0, 1] {
+                if result.total() >= self.settings.games { break 'openings; }
+                let game = self.play_game(opening, black);
+                let first_engine_color = if black == 0 { BLACK } else { WHITE };
+                if game.winner == 0 { result.draws += 1; }
+                else if game.winner == first_engine_color { result.wins += 1; }
+                else { result.losses += 1; }
+                result.games.push(game);
+            }
+            if let Some((elo0, elo1, alpha, beta)) = self.settings.sprt {
+                if result.sprt(elo0, elo1, alpha, beta) != SprtVerdict::Continue { break; }
+            }
+        }
+        result
+    }
+
+    fn play_game(&mut self, opening: &str, black: usize) -> GameRecord {
+        let mut game = Shobu::from_string(opening);
+        let mut moves = Vec::new();
+        let mut winner = 0;
+        for engine in self.engines.iter_mut() {
+            engine.new_game();
+        }
+        while moves.len() < self.settings.max_moves {
+            // player without legal move loses
+            if game.count_legal_moves() == 0 {
+                winner = -game.active_player;
+                break;
+            }
+            let engine = if game.active_player == BLACK { black } else { 1 - black };
+            let start = Instant::now();
+            let mv = self.engines[engine].choose_move(&mut game, &self.settings.limits);
+            let overtime = self.settings.limits.movetime.is_some_and(|movetime| start.elapsed().as_millis() > movetime + MOVE_TIME_MARGIN);
+            let mv_ext = match game.validate_and_extend(&mv) {
+                Ok(mv_ext) if !overtime => mv_ext,
+                _ => {
+                    winner = -game.active_player;
+                    break;
+                }
+            };
+            moves.push(mv_ext.to_string(game.active_player));
+            game.make_move_unsafe(&mv);
+            self.engines[1 - engine].notify_opponent_move(&game, &mv);
+            if game.winner != 0 {
+                winner = game.winner;
+                break;
+            }
+        }
+        for engine in self.engines.iter_mut() {
+            engine.game_over(winner);
+        }
+        GameRecord { opening: opening.to_string(), black, moves, winner }
+    }
+}
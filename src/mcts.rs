@@ -0,0 +1,169 @@
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::bot::ShobuBot;
+use crate::bot_constants::MS_PER_MOVE;
+use crate::eval_params::EvalParams;
+use crate::search_limits::SearchLimits;
+use crate::shobu::{Shobu, WHITE};
+use crate::shobu_move::Move;
+use crate::time_manager::TimeManager;
+use crate::utils::sigmoid;
+
+pub const EXPLORATION: f64 = 1.41;
+// random playouts are cut off and scored by eval
+const PLAYOUT_PLIES: usize = 40;
+const EVAL_SCALING: f64 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Playout {
+    // random moves until game end or cutoff
+    Random,
+    // leaf scored by eval without playout
+    Eval
+}
+
+struct Node {
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    // sum of results for player who moved into node
+    reward: f64
+}
+
+pub struct MctsResult {
+    pub best_move: Move,
+    pub visits: u32,
+    pub win_rate: f64,
+    pub iterations: usize
+}
+
+// uct search over tree stored in arena, rebuilt for every move
+pub struct MctsBot {
+    exploration: f64,
+    playout: Playout,
+    evaluator: ShobuBot,
+    rng: StdRng,
+    nodes: Vec<Node>
+}
+
+impl MctsBot {
+    pub fn new(exploration: f64, playout: Playout) -> Self {
+        MctsBot {
+            exploration,
+            playout,
+            evaluator: ShobuBot::evaluator(EvalParams::new()),
+            rng: StdRng::from_entropy(),
+            nodes: Vec::new()
+        }
+    }
+
+    pub fn set_params(&mut self, params: EvalParams) {
+        self.evaluator.set_params(params);
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // nodes limit counts iterations, depth limit is ignored and search without other limits takes default move time
+    pub fn search(&mut self, position: &mut Shobu, limits: &SearchLimits) -> MctsResult {
+        let start_time = Instant::now();
+        let time_manager = limits.clock.map(|clock| TimeManager::new(&clock, position.active_player));
+        self.nodes = vec![self.new_node(position, None, None)];
+        let mut iterations = 0;
+        loop {
+            self.iteration(position);
+            iterations += 1;
+            if limits.stop.as_ref().is_some_and(|x| x.load(Ordering::Relaxed)) { break; }
+            if limits.infinite { continue; }
+            if limits.nodes.is_some_and(|nodes| iterations >= nodes) { break; }
+            let elapsed = start_time.elapsed().as_millis();
+            if limits.movetime.is_some_and(|movetime| elapsed >= movetime) { break; }
+            if time_manager.as_ref().is_some_and(|x| elapsed >= x.optimum()) { break; }
+            if limits.nodes.is_none() && limits.movetime.is_none() && time_manager.is_none() && elapsed >= MS_PER_MOVE { break; }
+        }
+        let root = &self.nodes[0];
+        let best = *root.children.iter().max_by_key(|&&child| self.nodes[child].visits)
+            .expect("Position has no legal moves!");
+        let best = &self.nodes[best];
+        MctsResult {
+            best_move: best.mv.as_ref().unwrap().deep_copy(),
+            visits: best.visits,
+            win_rate: best.reward / f64::max(best.visits as f64, 1.0),
+            iterations
+        }
+    }
+
+    fn new_node(&self, position: &Shobu, mv: Option<Move>, parent: Option<usize>) -> Node {
+        let untried = if position.winner == 0 { position.get_legal_moves().into_iter().map(|x| x.mv).collect() } else { Vec::new() };
+        Node { mv, parent, children: Vec::new(), untried, visits: 0, reward: 0.0 }
+    }
+
+    // selection, expansion, playout and backpropagation, position is restored afterwards
+    fn iteration(&mut self, position: &mut Shobu) {
+        let mut node = 0;
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select_child(node);
+            position.make_move_unsafe(self.nodes[node].mv.as_ref().unwrap());
+        }
+        if !self.nodes[node].untried.is_empty() {
+            let index = self.rng.gen_range(0..self.nodes[node].untried.len());
+            let mv = self.nodes[node].untried.swap_remove(index);
+            position.make_move_unsafe(&mv);
+            let child = self.new_node(position, Some(mv), Some(node));
+            self.nodes.push(child);
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+            node = child;
+        }
+        let white_result = self.playout(position);
+        loop {
+            // player who moved into node is not on move
+            let reward = if position.active_player == WHITE { 1.0 - white_result } else { white_result };
+            self.nodes[node].visits += 1;
+            self.nodes[node].reward += reward;
+            let Some(parent) = self.nodes[node].parent else { break; };
+            position.undo_move();
+            node = parent;
+        }
+    }
+
+    fn select_child(&self, node: usize) -> usize {
+        let log_visits = f64::ln(self.nodes[node].visits as f64);
+        *self.nodes[node].children.iter().max_by(|&&a, &&b| {
+            self.uct(a, log_visits).total_cmp(&self.uct(b, log_visits))
+        }).unwrap()
+    }
+
+    fn uct(&self, node: usize, log_parent_visits: f64) -> f64 {
+        let node = &self.nodes[node];
+        let visits = node.visits as f64;
+        node.reward / visits + self.exploration * f64::sqrt(log_parent_visits / visits)
+    }
+
+    // result from white perspective, 1.0 win, 0.0 loss
+    fn playout(&mut self, position: &mut Shobu) -> f64 {
+        let mut plies = 0;
+        if self.playout == Playout::Random {
+            while position.winner == 0 && plies < PLAYOUT_PLIES {
+                let moves = position.legal_move_list();
+                if moves.is_empty() { break; }
+                position.make_move_unsafe(&moves.get(self.rng.gen_range(0..moves.len())).mv);
+                plies += 1;
+            }
+        }
+        let result = match position.winner {
+            // player without legal move loses
+            0 if position.count_legal_moves() == 0 => if position.active_player == WHITE { 0.0 } else { 1.0 },
+            0 => sigmoid(self.evaluator.eval(position), EVAL_SCALING),
+            winner => if winner == WHITE { 1.0 } else { 0.0 }
+        };
+        for _ in 0..plies {
+            position.undo_move();
+        }
+        result
+    }
+}
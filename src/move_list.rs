@@ -0,0 +1,104 @@
+use crate::packed_move::PackedMove;
+use crate::shobu::Shobu;
+use crate::shobu_move::MoveExtended;
+
+// candidate moves of 3 board pairs, 8 directions, 2 distances and 4 stones on both boards
+pub const MAX_MOVES: usize = 3 * 8 * 2 * 4 * 4;
+
+// stages of staged generation, in order they are searched
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveKind {
+    EjectingPush,
+    Push,
+    Quiet
+}
+
+#[derive(Clone, Copy)]
+struct ListedMove {
+    mv: PackedMove,
+    push_1: bool,
+    push_2: bool
+}
+
+const EMPTY_MOVE: ListedMove = ListedMove { mv: PackedMove::EMPTY, push_1: false, push_2: false };
+
+// legal moves packed in fixed capacity buffer on stack
+pub struct MoveList {
+    moves: [ListedMove; MAX_MOVES],
+    len: usize
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList { moves: [EMPTY_MOVE; MAX_MOVES], len: 0 }
+    }
+
+    pub fn push(&mut self, mv: &MoveExtended) {
+        self.moves[self.len] = ListedMove { mv: PackedMove::new(&mv.mv), push_1: mv.push_1, push_2: mv.push_2 };
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> MoveExtended {
+        let listed = &self.moves[index];
+        MoveExtended { mv: listed.mv.to_move(), push_1: listed.push_1, push_2: listed.push_2 }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = MoveExtended> + '_ {
+        (0..self.len).map(|x| self.get(x))
+    }
+}
+
+// hash move first, then ejecting pushes, other pushes and quiet moves
+// each kind is generated only when previous ones are exhausted without cutoff
+pub struct StagedMoves {
+    tt_move: Option<MoveExtended>,
+    tt_move_done: bool,
+    // kind of generated moves, None before first stage
+    kind: Option<MoveKind>,
+    moves: Option<MoveList>,
+    index: usize
+}
+
+impl StagedMoves {
+    // hash move must be legal in position
+    pub fn new(tt_move: Option<MoveExtended>) -> Self {
+        StagedMoves { tt_move, tt_move_done: false, kind: None, moves: None, index: 0 }
+    }
+
+    // position must be the same on every call
+    pub fn next(&mut self, position: &Shobu) -> Option<MoveExtended> {
+        if !self.tt_move_done {
+            self.tt_move_done = true;
+            if let Some(mv) = &self.tt_move {
+                return Some(MoveExtended::new(&mv.mv, mv.push_1, mv.push_2));
+            }
+        }
+        loop {
+            if let Some(moves) = &self.moves {
+                while self.index < moves.len() {
+                    let mv = moves.get(self.index);
+                    self.index += 1;
+                    if self.tt_move.as_ref().is_some_and(|x| x.mv.is_same(&mv.mv)) { continue; }
+                    return Some(mv);
+                }
+            }
+            let kind = match self.kind {
+                None => MoveKind::EjectingPush,
+                Some(MoveKind::EjectingPush) => MoveKind::Push,
+                Some(MoveKind::Push) => MoveKind::Quiet,
+                Some(MoveKind::Quiet) => return None
+            };
+            self.kind = Some(kind);
+            self.moves = Some(position.move_list(kind));
+            self.index = 0;
+        }
+    }
+}
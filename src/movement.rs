@@ -0,0 +1,76 @@
+use crate::shobu::DIRECTIONS;
+
+// margin tile, empty board value there is never occupied
+pub const OFF_BOARD: usize = 0;
+const OFF: u8 = OFF_BOARD as u8;
+
+// tiles reached by moving stone from tile in direction, off board ones are OFF_BOARD
+#[derive(Clone, Copy)]
+struct PackedStep {
+    to: u8,
+    over: u8,
+    landing: u8
+}
+
+pub struct Step {
+    pub to: usize,
+    // jumped over tile of double move
+    pub over: usize,
+    // where stone pushed from to or over lands
+    pub landing: usize
+}
+
+const fn playable(tile: i8) -> bool {
+    tile >= 0 && tile < 36 && tile / 6 >= 1 && tile / 6 <= 4 && tile % 6 >= 1 && tile % 6 <= 4
+}
+
+const fn on_board(tile: i8) -> u8 {
+    if playable(tile) { tile as u8 } else { OFF }
+}
+
+// indexed by tile, direction id and double
+const fn steps() -> [[[PackedStep; 2]; 8]; 36] {
+    let off_board = PackedStep { to: OFF, over: OFF, landing: OFF };
+    let mut steps = [[[off_board; 2]; 8]; 36];
+    let mut from = 0;
+    while from < 36 {
+        let mut direction_id = 0;
+        while direction_id < 8 {
+            let direction = DIRECTIONS[direction_id];
+            let next = from as i8 + direction;
+            let far = next + direction;
+            if playable(from as i8) && playable(next) {
+                steps[from][direction_id][0] = PackedStep { to: next as u8, over: OFF, landing: on_board(far) };
+                if playable(far) {
+                    steps[from][direction_id][1] = PackedStep { to: far as u8, over: next as u8, landing: on_board(far + direction) };
+                }
+            }
+            direction_id += 1;
+        }
+        from += 1;
+    }
+    steps
+}
+
+static STEPS: [[[PackedStep; 2]; 8]; 36] = steps();
+
+// direction id by direction offset by 7
+const DIRECTION_IDS: [usize; 15] = {
+    let mut ids = [0; 15];
+    let mut direction_id = 0;
+    while direction_id < 8 {
+        ids[(DIRECTIONS[direction_id] + 7) as usize] = direction_id;
+        direction_id += 1;
+    }
+    ids
+};
+
+// index of direction in DIRECTIONS
+pub fn direction_id(direction: i8) -> usize {
+    DIRECTION_IDS[(direction + 7) as usize]
+}
+
+pub fn step(from: usize, direction: i8, double: bool) -> Step {
+    let step = STEPS[from][direction_id(direction)][double as usize];
+    Step { to: step.to as usize, over: step.over as usize, landing: step.landing as usize }
+}
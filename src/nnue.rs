@@ -0,0 +1,190 @@
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::shobu::{Shobu, BLACK, TILES, WHITE};
+use crate::shobu_move::internal_2_readable;
+
+// stone of each colour on each tile of each board
+pub const INPUTS: usize = 128;
+const INITIAL_WEIGHT_RANGE: f32 = 0.1;
+
+pub fn feature(player: i8, board_id: usize, tile: usize) -> usize {
+    let player_id = if player == BLACK {0} else {1};
+    (player_id * 4 + board_id) * 16 + internal_2_readable(tile)
+}
+
+// dense layer, weights stored input major so first layer columns are added per feature
+struct Layer {
+    inputs: usize,
+    outputs: usize,
+    weights: Vec<f32>,
+    biases: Vec<f32>
+}
+
+impl Layer {
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let mut output = self.biases.clone();
+        for (i, value) in input.iter().enumerate() {
+            if *value == 0.0 { continue; }
+            let column = &self.weights[i * self.outputs..(i + 1) * self.outputs];
+            for (out, weight) in output.iter_mut().zip(column) {
+                *out += value * weight;
+            }
+        }
+        output
+    }
+}
+
+fn relu(values: &mut [f32]) {
+    for value in values.iter_mut() {
+        *value = f32::max(*value, 0.0);
+    }
+}
+
+// evaluation from white perspective, first layer is kept in accumulator of position
+pub struct Network {
+    layers: Vec<Layer>
+}
+
+impl Network {
+    // sizes of layers after inputs, last one must be 1
+    fn new(sizes: &[usize], mut weight: impl FnMut() -> f32) -> Result<Self, String> {
+        if sizes.is_empty() || sizes.last() != Some(&1) { return Err("Last layer must have single output!".to_string()); }
+        let mut inputs = INPUTS;
+        let mut layers = Vec::new();
+        for &outputs in sizes {
+            layers.push(Layer {
+                inputs,
+                outputs,
+                weights: (0..inputs * outputs).map(|_| weight()).collect(),
+                biases: vec![0.0; outputs]
+            });
+            inputs = outputs;
+        }
+        Ok(Network { layers })
+    }
+
+    pub fn random(sizes: &[usize], seed: u64) -> Result<Self, String> {
+        let mut rand = StdRng::seed_from_u64(seed);
+        Self::new(sizes, || rand.gen_range(-INITIAL_WEIGHT_RANGE..INITIAL_WEIGHT_RANGE))
+    }
+
+    fn accumulator_size(&self) -> usize {
+        self.layers[0].outputs
+    }
+
+    fn column(&self, feature: usize) -> &[f32] {
+        let size = self.accumulator_size();
+        &self.layers[0].weights[feature * size..(feature + 1) * size]
+    }
+
+    // remaining layers applied to first layer output
+    fn output(&self, accumulator: &[f32]) -> f64 {
+        let mut values = accumulator.to_vec();
+        for layer in &self.layers[1..] {
+            relu(&mut values);
+            values = layer.forward(&values);
+        }
+        values[0] as f64
+    }
+
+    // active features and first layer output computed from scratch
+    fn accumulate(&self, position: &Shobu) -> (u128, Vec<f32>) {
+        let mut features = 0;
+        let mut accumulator = self.layers[0].biases.clone();
+        for (board_id, board) in position.boards.iter().enumerate() {
+            for tile in TILES {
+                if board[tile] != BLACK && board[tile] != WHITE { continue; }
+                let feature = feature(board[tile], board_id, tile);
+                features |= 1 << feature;
+                for (value, weight) in accumulator.iter_mut().zip(self.column(feature)) {
+                    *value += weight;
+                }
+            }
+        }
+        (features, accumulator)
+    }
+
+    pub fn evaluate(&self, position: &Shobu) -> f64 {
+        self.output(&self.accumulate(position).1)
+    }
+
+    // "layers n1 n2 ..." line, then "weights" and "biases" lines of every layer
+    pub fn from_string(string: &str) -> Result<Self, String> {
+        let mut lines = string.lines().map(|x| x.trim()).filter(|x| !x.is_empty() && !x.starts_with('#'));
+        let mut next = |name: &str| -> Result<Vec<&str>, String> {
+            let line = lines.next().ok_or(format!("Missing {} line!", name))?;
+            let mut parts = line.split_whitespace();
+            if parts.next() != Some(name) { return Err(format!("Expected {} line!", name)); }
+            Ok(parts.collect())
+        };
+        let sizes: Vec<usize> = next("layers")?.iter().map(|x| x.parse().map_err(|_| format!("Invalid layer size {}!", x))).collect::<Result<_, _>>()?;
+        let parse = |values: Vec<&str>, len: usize| -> Result<Vec<f32>, String> {
+            let values: Vec<f32> = values.iter().map(|x| x.parse().map_err(|_| format!("Invalid value {}!", x))).collect::<Result<_, _>>()?;
+            if values.len() != len { return Err(format!("Expected {} values!", len)); }
+            Ok(values)
+        };
+        let mut network = Network::new(&sizes, || 0.0)?;
+        for layer in network.layers.iter_mut() {
+            layer.weights = parse(next("weights")?, layer.inputs * layer.outputs)?;
+            layer.biases = parse(next("biases")?, layer.outputs)?;
+        }
+        Ok(network)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_string(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sizes: Vec<String> = self.layers.iter().map(|x| x.outputs.to_string()).collect();
+        writeln!(f, "layers {}", sizes.join(" "))?;
+        for layer in &self.layers {
+            let weights: Vec<String> = layer.weights.iter().map(|x| x.to_string()).collect();
+            let biases: Vec<String> = layer.biases.iter().map(|x| x.to_string()).collect();
+            writeln!(f, "weights {}", weights.join(" "))?;
+            writeln!(f, "biases {}", biases.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+// first layer output of position, updated with every stone change
+#[derive(Clone)]
+pub struct NnueState {
+    network: Arc<Network>,
+    features: u128,
+    accumulator: Vec<f32>
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>, position: &Shobu) -> Self {
+        let (features, accumulator) = network.accumulate(position);
+        NnueState { network, features, accumulator }
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    // feature is added when absent and removed when present, matching zobrist updates
+    pub fn toggle(&mut self, feature: usize) {
+        let present = self.features >> feature & 1 == 1;
+        self.features ^= 1 << feature;
+        let column = self.network.column(feature);
+        for (value, weight) in self.accumulator.iter_mut().zip(column) {
+            if present { *value -= weight; } else { *value += weight; }
+        }
+    }
+
+    pub fn evaluate(&self) -> f64 {
+        self.network.output(&self.accumulator)
+    }
+}
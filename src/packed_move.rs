@@ -0,0 +1,41 @@
+use crate::movement;
+use crate::shobu::{DIRECTIONS, TILES};
+use crate::shobu_move::{internal_2_readable, Move};
+
+// boards 2 bits each, direction id 3 bits, readable tiles 4 bits each and double flag
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PackedMove(u16);
+
+impl PackedMove {
+    pub const EMPTY: PackedMove = PackedMove(0);
+
+    pub fn new(mv: &Move) -> Self {
+        let direction_id = movement::direction_id(mv.direction) as u16;
+        PackedMove(mv.board_1 as u16
+            | (mv.board_2 as u16) << 2
+            | direction_id << 4
+            | (internal_2_readable(mv.from_1) as u16) << 7
+            | (internal_2_readable(mv.from_2) as u16) << 11
+            | (mv.double as u16) << 15)
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        PackedMove(bits)
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn to_move(self) -> Move {
+        let bits = self.0 as usize;
+        Move {
+            board_1: bits & 3,
+            board_2: bits >> 2 & 3,
+            direction: DIRECTIONS[bits >> 4 & 7],
+            from_1: TILES[bits >> 7 & 15],
+            from_2: TILES[bits >> 11 & 15],
+            double: bits >> 15 & 1 == 1
+        }
+    }
+}
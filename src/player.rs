@@ -0,0 +1,146 @@
+use std::io;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::bot::ShobuBot;
+use crate::bot_constants::INF;
+use crate::eval_params::EvalParams;
+use crate::mcts::{self, MctsBot, Playout};
+use crate::search_limits::SearchLimits;
+use crate::shobu::{Shobu, WHITE};
+use crate::shobu_move::Move;
+use crate::utils;
+
+// anything able to play a game, position is left unchanged by choose_move
+pub trait Player {
+    fn choose_move(&mut self, position: &mut Shobu, limits: &SearchLimits) -> Move;
+
+    // before first move of every game
+    fn new_game(&mut self) {}
+
+    // position after opponent move
+    fn notify_opponent_move(&mut self, _position: &Shobu, _mv: &Move) {}
+
+    // winner is 0 for draw
+    fn game_over(&mut self, _winner: i8) {}
+}
+
+impl Player for ShobuBot {
+    fn choose_move(&mut self, position: &mut Shobu, limits: &SearchLimits) -> Move {
+        ShobuBot::choose_move(self, position, limits)
+    }
+
+    // games are played independently of each other
+    fn new_game(&mut self) {
+        self.clear_hash();
+    }
+
+    fn game_over(&mut self, _winner: i8) {
+        self.stop_pondering();
+    }
+}
+
+impl Player for MctsBot {
+    fn choose_move(&mut self, position: &mut Shobu, limits: &SearchLimits) -> Move {
+        self.search(position, limits).best_move
+    }
+}
+
+pub struct RandomBot {
+    rng: StdRng
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        RandomBot { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Player for RandomBot {
+    fn choose_move(&mut self, position: &mut Shobu, _limits: &SearchLimits) -> Move {
+        let moves = position.legal_move_list();
+        moves.get(self.rng.gen_range(0..moves.len())).mv
+    }
+}
+
+// plays move with best eval after it, winning moves first
+pub struct GreedyBot {
+    evaluator: ShobuBot
+}
+
+impl GreedyBot {
+    pub fn new(params: EvalParams) -> Self {
+        GreedyBot { evaluator: ShobuBot::evaluator(params) }
+    }
+}
+
+impl Player for GreedyBot {
+    fn choose_move(&mut self, position: &mut Shobu, _limits: &SearchLimits) -> Move {
+        let player = position.active_player;
+        let mut best: Option<(f64, Move)> = None;
+        for mv in position.get_legal_moves() {
+            position.make_move_unsafe(&mv.mv);
+            let eval = if position.winner == player { INF } else { self.evaluator.eval(position) * player as f64 };
+            position.undo_move();
+            if best.as_ref().is_none_or(|(best_eval, _)| eval > *best_eval) {
+                best = Some((eval, mv.mv));
+            }
+        }
+        best.expect("Position has no legal moves!").1
+    }
+}
+
+// reads moves in notation from standard input
+pub struct HumanPlayer;
+
+impl Player for HumanPlayer {
+    fn choose_move(&mut self, position: &mut Shobu, _limits: &SearchLimits) -> Move {
+        let stdin = io::stdin();
+        loop {
+            println!("{}", position.to_string());
+            println!("your move:");
+            let encoded = utils::input(&stdin);
+            match Move::from_string(encoded.trim(), position.active_player) {
+                Some(mv) if position.validate_and_extend(&mv).is_ok() => return mv,
+                _ => println!("Invalid move {}!", encoded)
+            }
+        }
+    }
+
+    fn notify_opponent_move(&mut self, position: &Shobu, mv: &Move) {
+        // opponent is not on move anymore, so notation is taken from position before move
+        let mut before = position.clone();
+        if before.history.is_empty() { return; }
+        before.undo_move();
+        if let Ok(mv) = before.validate_and_extend(mv) {
+            println!("opponent played {}", mv.to_string(before.active_player));
+        }
+    }
+
+    fn game_over(&mut self, winner: i8) {
+        match winner {
+            0 => println!("draw"),
+            WHITE => println!("white won"),
+            _ => println!("black won")
+        }
+    }
+}
+
+// player by name: minimax, mcts, greedy, random or human
+pub fn create_player(kind: &str, params: EvalParams, threads: usize) -> Result<Box<dyn Player>, String> {
+    match kind {
+        "minimax" => {
+            let mut bot = ShobuBot::new();
+            bot.set_params(params);
+            bot.set_threads(threads);
+            Ok(Box::new(bot))
+        },
+        "mcts" => {
+            let mut bot = MctsBot::new(mcts::EXPLORATION, Playout::Random);
+            bot.set_params(params);
+            Ok(Box::new(bot))
+        },
+        "greedy" => Ok(Box::new(GreedyBot::new(params))),
+        "random" => Ok(Box::new(RandomBot::new(rand::random()))),
+        "human" => Ok(Box::new(HumanPlayer)),
+        _ => Err(format!("Unknown player {}!", kind))
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use crate::bot_constants::MS_PER_MOVE;
+use crate::shobu_move::Move;
+use crate::time_manager::GameClock;
+
+// conditions ending search, search stops when any of set limits is reached
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub nodes: Option<usize>,
+    pub movetime: Option<u128>,
+    pub infinite: bool,
+    pub stop: Option<Arc<AtomicBool>>,
+    pub clock: Option<GameClock>,
+    // root only considers these moves
+    pub search_moves: Option<Vec<Move>>,
+    pub exclude_moves: Vec<Move>
+}
+
+impl SearchLimits {
+    pub fn new() -> Self {
+        SearchLimits {
+            depth: None,
+            nodes: None,
+            movetime: Some(MS_PER_MOVE),
+            infinite: false,
+            stop: None,
+            clock: None,
+            search_moves: None,
+            exclude_moves: Vec::new()
+        }
+    }
+
+    pub fn depth(depth: usize) -> Self {
+        SearchLimits { depth: Some(depth), movetime: None, ..Self::new() }
+    }
+
+    pub fn nodes(nodes: usize) -> Self {
+        SearchLimits { nodes: Some(nodes), movetime: None, ..Self::new() }
+    }
+
+    pub fn movetime(movetime: u128) -> Self {
+        SearchLimits { movetime: Some(movetime), ..Self::new() }
+    }
+
+    // time for move is allocated from remaining game time
+    pub fn clock(clock: GameClock) -> Self {
+        SearchLimits { movetime: None, clock: Some(clock), ..Self::new() }
+    }
+
+    // runs until stop is set, other limits are ignored
+    pub fn infinite(stop: Arc<AtomicBool>) -> Self {
+        SearchLimits { movetime: None, infinite: true, stop: Some(stop), ..Self::new() }
+    }
+}
+
+impl Clone for SearchLimits {
+    fn clone(&self) -> Self {
+        SearchLimits {
+            stop: self.stop.clone(),
+            search_moves: self.search_moves.as_ref().map(|moves| moves.iter().map(|x| x.deep_copy()).collect()),
+            exclude_moves: self.exclude_moves.iter().map(|x| x.deep_copy()).collect(),
+            ..*self
+        }
+    }
+}
+
+pub fn parse_moves(encoded: &str, active_player: i8) -> Result<Vec<Move>, String> {
+    encoded.split(',').map(|x| Move::from_string(x.trim(), active_player).ok_or(format!("Invalid move {}!", x))).collect()
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+use crate::bot_constants::{MAX_PLY, WIN_EVAL};
+use crate::search_stats::SearchStats;
+use crate::shobu::Shobu;
+use crate::shobu_move::Move;
+
+// eval from side to move perspective, wins counted in own moves
+pub enum Score {
+    Eval(f64),
+    WinIn(usize),
+    LossIn(usize)
+}
+
+impl Score {
+    pub fn from_eval(eval: f64) -> Self {
+        if eval >= WIN_EVAL - MAX_PLY as f64 {
+            let plies = (WIN_EVAL - eval).round() as usize;
+            Score::WinIn(plies.div_ceil(2))
+        } else if eval <= -WIN_EVAL + MAX_PLY as f64 {
+            let plies = (WIN_EVAL + eval).round() as usize;
+            Score::LossIn(plies / 2)
+        } else {
+            Score::Eval(eval)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Eval(eval) => write!(f, "{:+.2}", eval),
+            Score::WinIn(moves) => write!(f, "win in {} moves", moves),
+            Score::LossIn(moves) => write!(f, "loss in {} moves", moves)
+        }
+    }
+}
+
+pub struct SearchResult {
+    pub best_move: Move,
+    pub eval: f64,
+    pub depth: usize,
+    pub pv: Vec<Move>,
+    // ranked root moves with evals, more than one only in multipv search
+    pub lines: Vec<(Move, f64)>,
+    // every searched root move with eval from last finished iteration
+    pub root_moves: Vec<(Move, f64)>,
+    pub iterations: Vec<SearchStats>,
+    // position was pondered on, expected opponent reply was played
+    pub ponder_hit: bool
+}
+
+pub struct AnalysisLine {
+    pub mv: String,
+    pub score: Score,
+    pub pv: Vec<String>,
+    pub depth: usize
+}
+
+pub fn moves_to_string(position: &Shobu, moves: &[Move]) -> Vec<String> {
+    let mut position = position.clone();
+    let mut encoded = Vec::new();
+    for mv in moves {
+        match position.validate_and_extend(mv) {
+            Ok(mv_ext) => encoded.push(mv_ext.to_string(position.active_player)),
+            Err(_) => break
+        }
+        position.make_move_unsafe(mv);
+    }
+    encoded
+}
+
+impl SearchResult {
+    pub fn score(&self) -> Score {
+        Score::from_eval(self.eval)
+    }
+
+    pub fn pv_to_string(&self, position: &Shobu) -> Vec<String> {
+        moves_to_string(position, &self.pv)
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+
+// diagnostics of single iteration of iterative deepening
+#[derive(Clone, Default)]
+pub struct SearchStats {
+    pub depth: usize,
+    pub seldepth: usize,
+    pub nodes: usize,
+    pub time: u128,
+    pub total_time: u128,
+    pub tt_probes: usize,
+    pub tt_hits: usize,
+    pub tt_cutoffs: usize,
+    pub beta_cutoffs: usize,
+    pub first_move_cutoffs: usize,
+    pub tb_hits: usize,
+    pub branching_factor: f64
+}
+
+impl SearchStats {
+    pub fn nps(&self) -> f64 {
+        if self.time == 0 { return 0.0; }
+        self.nodes as f64 * 1000.0 / self.time as f64
+    }
+
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.beta_cutoffs == 0 { return 0.0; }
+        self.first_move_cutoffs as f64 / self.beta_cutoffs as f64
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "depth {} seldepth {} nodes {} time {} total {} nps {:.0} tt probes {} hits {} cutoffs {} first move cutoffs {:.1}% ebf {:.2} tb hits {}",
+            self.depth, self.seldepth, self.nodes, self.time, self.total_time, self.nps(),
+            self.tt_probes, self.tt_hits, self.tt_cutoffs, 100.0 * self.first_move_cutoff_rate(), self.branching_factor, self.tb_hits)
+    }
+}
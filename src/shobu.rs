@@ -1,6 +1,11 @@
 use std::hash;
 
+use std::sync::Arc;
+use crate::nnue::{self, Network, NnueState};
 use crate::shobu_move::{self, internal_2_readable, Move, MoveExtended};
+use crate::move_list::{MoveKind, MoveList};
+use crate::movement::{self, OFF_BOARD};
+use crate::packed_move::PackedMove;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 
 pub const BLACK: i8 = -1;
@@ -21,21 +26,72 @@ const SHIFTS: [[[usize; 8]; 2]; 2] = [
 ];
 const ZOBRIST_TILES: [usize; 16] = [0, 1, 1, 0, 2, 3, 3, 2, 4, 5, 5, 4, 6, 7, 7, 6];
 
+// pushes player could make if on move, ejectable holds bitmask of opponent stone tiles per board
+pub struct Threats {
+    pub ejectable: [u64; 4],
+    pub pushes: usize
+}
+
+// legal moves of one player per board, options are bits of direction id * 2 + double
+pub struct MoveSummary {
+    // options without push
+    quiet: [u16; 4],
+    legal: [u16; 4],
+    pushes: [[u8; 16]; 4],
+    // opponent stone tiles ejected by push of option
+    ejectable: [[u64; 16]; 4],
+    // dark first
+    home_boards: [usize; 2]
+}
+
+impl MoveSummary {
+    pub fn passive_directions(&self, board_id: usize) -> usize {
+        self.quiet[board_id].count_ones() as usize
+    }
+
+    pub fn move_directions(&self, board_id: usize) -> usize {
+        let aggressive = self.legal[1 - board_id % 2] | self.legal[3 - board_id % 2];
+        (self.quiet[board_id] & aggressive).count_ones() as usize
+    }
+
+    pub fn threats(&self) -> Threats {
+        let mut threats = Threats { ejectable: [0; 4], pushes: 0 };
+        for board_id in 0..4 {
+            let passive = self.quiet[self.home_boards[1 - board_id % 2]];
+            for option in 0..16 {
+                if passive >> option & 1 == 0 { continue; }
+                threats.pushes += self.pushes[board_id][option] as usize;
+                threats.ejectable[board_id] |= self.ejectable[board_id][option];
+            }
+        }
+        threats
+    }
+}
+
+#[derive(Clone)]
 pub struct Shobu {
     pub active_player: i8,
     pub winner: i8,
     pub boards: [[i8; 36]; 4],
     pub pieces: [[[usize; 4]; 4]; 2],
-    pub history: Vec<(Move, usize, usize)>,
+    // packed move, board of pushed stone and tile it was pushed from
+    pub history: Vec<(PackedMove, usize, usize)>,
     pub hashes: [[u64; 2]; 2],
     piece_hash_vals: [[u64; 8]; 2],
     black_active_hash: u64,
+    // network accumulator, updated together with hashes
+    pub nnue: Option<NnueState>
 }
 
 fn occupied(val: i8) -> bool {
     val == BLACK || val == WHITE
 }
 
+// assumes legal push, checks if pushed stone lands on margin
+fn push_ejects(direction: i8, from: usize, double: bool) -> bool {
+    movement::step(from, direction, double).landing == OFF_BOARD
+}
+
 impl Shobu {
     pub fn new() -> Self {
         let mut rand = StdRng::seed_from_u64(2137);
@@ -47,7 +103,8 @@ impl Shobu {
             history: Vec::new(),
             piece_hash_vals: rand.gen(),
             black_active_hash: rand.gen(),
-            hashes: [[0; 2]; 2]
+            hashes: [[0; 2]; 2],
+            nnue: None
         };
         new.init();
         new.init_hashes();
@@ -85,22 +142,23 @@ impl Shobu {
         self.active_player = -self.active_player;
         // add to history
         if pushed_from_1 != NOT_ON_BOARD {
-            self.history.push((mv.deep_copy(), mv.board_1, pushed_from_1))
+            self.history.push((PackedMove::new(mv), mv.board_1, pushed_from_1))
         } else {
             // if push occured on board_2 or neither move was push
-            self.history.push((mv.deep_copy(),  mv.board_2, pushed_from_2))
+            self.history.push((PackedMove::new(mv), mv.board_2, pushed_from_2))
         }
     }
 
     pub fn available_passive_directions(&self, board_id: usize, active_player: i8) -> usize {
         let player_id = if active_player == BLACK {0} else {1};
         let mut dir_count = 0;
-        for double in [1, 2] {
+        let board = &self.boards[board_id];
+        for double in [false, true] {
             for direction in DIRECTIONS {
-                let diff = direction * double;
                 for piece in self.pieces[player_id][board_id] {
                     if piece == NOT_ON_BOARD {continue;}
-                    if piece as i8 + diff > 0 && piece as i8 + diff < 36 && self.boards[board_id][(piece as i8 + diff) as usize] == EMPTY && self.boards[board_id][(piece as i8 + direction) as usize] == EMPTY {
+                    let step = movement::step(piece, direction, double);
+                    if step.to != OFF_BOARD && board[step.to] == EMPTY && !occupied(board[step.over]) {
                         dir_count += 1;
                         break;
                     }
@@ -114,6 +172,7 @@ impl Shobu {
         self.winner = 0;
         self.active_player = -self.active_player;
         let (mv, pushed_board, pushed_from) = self.history.pop().unwrap();
+        let mv = mv.to_move();
         let diff = shobu_move::diff(mv.direction, mv.double);
         // undo moves
         self.boards[mv.board_1][mv.from_1] = self.active_player;
@@ -223,82 +282,165 @@ impl Shobu {
     }
 
     pub fn get_legal_moves(&self) -> Vec<MoveExtended> {
-        let mut res = Vec::new();
-        self.moves_for_board_pair(&mut res, 0, 3);
-        self.moves_for_board_pair(&mut res, 1, 2);
-        if self.active_player == BLACK {
-           self.moves_for_board_pair(&mut res, 0, 1);
-        } else {
-            self.moves_for_board_pair(&mut res, 2, 3);
+        let mut moves = Vec::new();
+        self.for_each_move(None, |mv| moves.push(mv));
+        moves
+    }
+
+    pub fn legal_move_list(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.for_each_move(None, |mv| moves.push(&mv));
+        moves
+    }
+
+    // legal moves of one kind, generated separately for staged search
+    pub fn move_list(&self, kind: MoveKind) -> MoveList {
+        let mut moves = MoveList::new();
+        self.for_each_move(Some(kind), |mv| moves.push(&mv));
+        moves
+    }
+
+    pub fn count_legal_moves(&self) -> usize {
+        let mut count = 0;
+        self.for_each_move(None, |_| count += 1);
+        count
+    }
+
+    // only moves pushing a stone off the board, used by quiescence search
+    pub fn get_ejecting_moves(&self) -> Vec<MoveExtended> {
+        let mut moves = Vec::new();
+        self.for_each_move(Some(MoveKind::EjectingPush), |mv| moves.push(mv));
+        moves
+    }
+
+    pub fn ejecting_move_list(&self) -> MoveList {
+        self.move_list(MoveKind::EjectingPush)
+    }
+
+    // legality of every stone is checked once per board pair, direction and distance, then legal stones are paired
+    fn for_each_move(&self, only: Option<MoveKind>, mut visit: impl FnMut(MoveExtended)) {
+        let player_id = if self.active_player == BLACK { 0 } else { 1 };
+        let home_pair = if self.active_player == BLACK { (0, 1) } else { (2, 3) };
+        for (board_1, board_2) in [(0, 3), (1, 2), home_pair] {
+            // aggressive move on home board only when passive one is on the other home board
+            let push_allowed = |board_id: usize| (board_1, board_2) == home_pair || board_id / 2 != player_id;
+            for direction in DIRECTIONS {
+                for double in [false, true] {
+                    // legal stones with push and eject flags
+                    let stones = |board_id: usize| {
+                        let mut stones = [(NOT_ON_BOARD, false, false); 4];
+                        let mut count = 0;
+                        for piece in self.pieces[player_id][board_id] {
+                            if piece == NOT_ON_BOARD { continue; }
+                            let Some((push, eject)) = self.stone_move(self.active_player, board_id, direction, piece, double) else { continue; };
+                            if push && !push_allowed(board_id) { continue; }
+                            stones[count] = (piece, push, eject);
+                            count += 1;
+                        }
+                        (stones, count)
+                    };
+                    // ejecting push needs a stone that would push off board on one of the boards
+                    let may_eject = |board_id: usize| push_allowed(board_id)
+                        && self.pieces[player_id][board_id].iter().any(|&x| x != NOT_ON_BOARD && push_ejects(direction, x, double));
+                    if only == Some(MoveKind::EjectingPush) && !may_eject(board_1) && !may_eject(board_2) { continue; }
+                    let (first, first_count) = stones(board_1);
+                    if first_count == 0 { continue; }
+                    let (second, second_count) = stones(board_2);
+                    for &(from_1, push_1, eject_1) in &first[..first_count] {
+                        for &(from_2, push_2, eject_2) in &second[..second_count] {
+                            // 2 aggressive moves
+                            if push_1 && push_2 { continue; }
+                            let kind = if eject_1 || eject_2 { MoveKind::EjectingPush }
+                                else if push_1 || push_2 { MoveKind::Push }
+                                else { MoveKind::Quiet };
+                            if only.is_some_and(|x| x != kind) { continue; }
+                            let mv = Move { board_1, board_2, direction, from_1, from_2, double };
+                            visit(MoveExtended { mv, push_1, push_2 });
+                        }
+                    }
+                }
+            }
         }
-        res
-    }
-
-    fn moves_for_board_pair(&self, out: &mut Vec<MoveExtended>, board_1: usize, board_2: usize) {
-        for direction in DIRECTIONS {
-            for double in [false, true] {
-                for piece_1 in self.pieces[if self.active_player == BLACK { 0 } else { 1 }][board_1] {
-                    if piece_1 == NOT_ON_BOARD { continue; }
-                    let (legal_1, push_1) = self.is_legal_and_push(board_1, direction, piece_1, double);
-                    if !legal_1 { continue; }
-                    for piece_2 in self.pieces[if self.active_player == BLACK { 0 } else { 1 }][board_2] {
-                        if piece_2 == NOT_ON_BOARD { continue; }
-                        let (legal_2, push_2) = self.is_legal_and_push(board_2, direction, piece_2, double);
-                        if !legal_2 { continue; }
-                        // 2 aggressive moves
-                        if push_1 && push_2 { continue; }
-                        // aggressive move on home board
-                        let board_sum = board_1 + board_2;
-                        if self.active_player == BLACK && board_sum != 1 {
-                            if (board_1 < 2 && push_1) || (board_2 < 2 && push_2) { continue; }
-                        } else if self.active_player == WHITE && board_sum != 5 {
-                            if (board_1 > 1 && push_1) || (board_2 > 1 && push_2) { continue; }
+    }
+
+    // every stone of player checked once on every board for each direction and distance
+    pub fn move_summary(&self, player: i8) -> MoveSummary {
+        let player_id = if player == BLACK { 0 } else { 1 };
+        let mut summary = MoveSummary {
+            quiet: [0; 4],
+            legal: [0; 4],
+            pushes: [[0; 16]; 4],
+            ejectable: [[0; 16]; 4],
+            home_boards: if player == BLACK { [0, 1] } else { [2, 3] }
+        };
+        for board_id in 0..4 {
+            for piece in self.pieces[player_id][board_id] {
+                if piece == NOT_ON_BOARD { continue; }
+                for (direction_id, direction) in DIRECTIONS.iter().enumerate() {
+                    for double in [false, true] {
+                        let Some((push, eject)) = self.stone_move(player, board_id, *direction, piece, double) else { continue; };
+                        let option = direction_id * 2 + double as usize;
+                        summary.legal[board_id] |= 1 << option;
+                        if !push {
+                            summary.quiet[board_id] |= 1 << option;
+                            continue;
+                        }
+                        summary.pushes[board_id][option] += 1;
+                        if eject {
+                            // pushed stone is on target tile or on jumped over tile
+                            let step = movement::step(piece, *direction, double);
+                            let pushed = if occupied(self.boards[board_id][step.to]) { step.to } else { step.over };
+                            summary.ejectable[board_id][option] |= 1 << pushed;
                         }
-                        let mv: Move = Move {
-                            board_1: board_1,
-                            board_2: board_2,
-                            direction: direction,
-                            from_1: piece_1,
-                            from_2: piece_2,
-                            double: double,
-                        };
-                        let res: MoveExtended = MoveExtended {
-                            mv: mv,
-                            push_1: push_1,
-                            push_2: push_2
-                        };
-                        out.push(res);
                     }
                 }
             }
         }
+        summary
+    }
+
+    // directions and distances with passive move on home board and aggressive move on board of the other colour
+    pub fn available_move_directions(&self, board_id: usize, player: i8) -> usize {
+        self.move_summary(player).move_directions(board_id)
+    }
+
+    // aggressive pushes with matching passive move on home board of the other colour
+    pub fn threats(&self, player: i8) -> Threats {
+        self.move_summary(player).threats()
     }
 
     fn is_legal_and_push(&self, board_id: usize, direction: i8, from: usize, double: bool) -> (bool, bool) {
-        let board: [i8; 36] = self.boards[board_id];
+        match self.stone_move(self.active_player, board_id, direction, from, double) {
+            Some((push, _)) => (true, push),
+            None => (false, false)
+        }
+    }
+
+    // push and eject flags of legal stone move, one table lookup for both
+    fn stone_move(&self, player: i8, board_id: usize, direction: i8, from: usize, double: bool) -> Option<(bool, bool)> {
+        let board = &self.boards[board_id];
         // invalid stone color
-        if board[from] != self.active_player { return (false, false); }
-        let diff = shobu_move::diff(direction, double);
-        let to = (from as i8 + diff) as usize;
+        if board[from] != player { return None; }
+        let step = movement::step(from, direction, double);
         // goes out of board
-        if to >= board.len() || board[to] == MARGIN { return (false, false); }
+        if step.to == OFF_BOARD { return None; }
         let mut pieces_on_path = 0;
         // check target tile
-        if occupied(board[to]) {
-            if board[to] == self.active_player { return (false, false); }
+        if occupied(board[step.to]) {
+            if board[step.to] == player { return None; }
             pieces_on_path += 1;
         }
-        // if double check tile on path
-        let next = i8::saturating_add(from as i8, direction) as usize;
-        if double && occupied(board[next]) {
-            if board[next] == self.active_player { return (false, false); }
+        // check tile on path, off board for single move
+        if occupied(board[step.over]) {
+            if board[step.over] == player { return None; }
             pieces_on_path += 1;
         }
         // double push
-        if pieces_on_path > 1 { return (false, false); }
+        if pieces_on_path > 1 { return None; }
+        if pieces_on_path == 0 { return Some((false, false)); }
         // push blocked
-        if pieces_on_path > 0 && occupied(board[i8::saturating_add(to as i8, direction) as usize]) { return (false, false); }
-        (true, pieces_on_path > 0)
+        if occupied(board[step.landing]) { return None; }
+        Some((true, step.landing == OFF_BOARD))
     }
 
     pub fn to_string(&self) -> String
@@ -329,7 +471,8 @@ impl Shobu {
             history: Vec::new(),
             piece_hash_vals: rand.gen(),
             black_active_hash: rand.gen(),
-            hashes: [[0; 2]; 2]
+            hashes: [[0; 2]; 2],
+            nnue: None
         };
         let pos = string.split(" ");
         for (i, part) in pos.into_iter().enumerate() {
@@ -361,8 +504,15 @@ impl Shobu {
         new
     }
     
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|x| NnueState::new(x, self));
+    }
+
     fn update_hashes(&mut self, player: i8, board_id: usize, tile: usize) {
         if tile == NOT_ON_BOARD { return; }
+        if let Some(state) = &mut self.nnue {
+            state.toggle(nnue::feature(player, board_id, tile));
+        }
         let tile_id = internal_2_readable(tile);
         let part_id: usize = 2 * board_id + (tile % 4) / 2;
         let player_id = if player == BLACK {0} else {1};
@@ -111,6 +111,12 @@ impl Move {
         mv
     }
 
+    // equal moves with boards in any order
+    pub fn is_same(&self, other: &Move) -> bool {
+        let parts = |mv: &Move| if mv.board_1 < mv.board_2 { (mv.board_1, mv.from_1, mv.board_2, mv.from_2) } else { (mv.board_2, mv.from_2, mv.board_1, mv.from_1) };
+        self.direction == other.direction && self.double == other.double && parts(self) == parts(other)
+    }
+
     pub fn deep_copy(&self) -> Self {
         Move {
             board_1: self.board_1,
@@ -131,7 +137,7 @@ impl Move {
             direction_chars.push(encoded[index..].chars().next()?);
             index += 1;
         }
-        let direction_id = DIRECTION_CODES.iter().position(|&x| x == &direction_chars).unwrap();
+        let direction_id = DIRECTION_CODES.iter().position(|&x| x == &direction_chars)?;
         let direction = DIRECTIONS[direction_id];
 
         let board_1 = if encoded[index..].starts_with('b') { 1 + active_player } else { 2 + active_player };
@@ -35,9 +35,14 @@ pub fn tile_flipped(tile: usize) -> usize {
 }
 
 pub fn transposition_symmetries(position: &Shobu, tt_entry: &TTEntry) -> Option<(bool, bool)> {
+    matching_symmetries(position, tt_entry.variation_hash)
+}
+
+// symmetry under which position has given variation hash
+pub fn matching_symmetries(position: &Shobu, variation_hash: u64) -> Option<(bool, bool)> {
     for color_swap in [false, true] {
         for horizontal_swap in [false, true] {
-            if position.get_symmetry_hash(color_swap, horizontal_swap) == tt_entry.variation_hash {
+            if position.get_symmetry_hash(color_swap, horizontal_swap) == variation_hash {
                 return Some((color_swap, horizontal_swap));
             }
         }
@@ -0,0 +1,472 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use rustc_hash::FxHashMap;
+use crate::bot_constants::WIN_EVAL;
+use crate::movement::{self, OFF_BOARD};
+use crate::shobu::{Shobu, BLACK, DIRECTIONS, EMPTY, MARGIN, NOT_ON_BOARD, TILES, WHITE};
+
+const MAGIC: &[u8; 4] = b"SHTB";
+pub const MAX_TABLEBASE_STONES: usize = 2;
+// stored value is distance to win in plies increased by one, 0 for unproven positions
+const MAX_DISTANCE: usize = u8::MAX as usize - 1;
+// attacker stones are stored as black, defender ones as white
+const ATTACKER: i8 = BLACK;
+const DEFENDER: i8 = WHITE;
+
+// proven result from side to move perspective, game ends at latest distance plies later
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TablebaseScore {
+    Win(usize),
+    Loss(usize)
+}
+
+impl TablebaseScore {
+    fn from_value(value: u8, attacker_to_move: bool) -> Option<Self> {
+        let distance = value.checked_sub(1)? as usize;
+        if attacker_to_move { Some(TablebaseScore::Win(distance)) } else { Some(TablebaseScore::Loss(distance)) }
+    }
+
+    fn distance(&self) -> usize {
+        match self {
+            TablebaseScore::Win(distance) | TablebaseScore::Loss(distance) => *distance
+        }
+    }
+
+    // same scale as win scores of negamax, game ends distance plies after ply
+    pub fn eval(&self, ply: usize) -> f64 {
+        match self {
+            TablebaseScore::Win(distance) => WIN_EVAL - (ply + distance) as f64,
+            TablebaseScore::Loss(distance) => -WIN_EVAL + (ply + distance) as f64
+        }
+    }
+}
+
+// home board of attacker and board paired with it, which is home board of defender
+// attacker plays only moves with passive part on first board and aggressive one on second, those depend on nothing else
+// defender may also pass or play any stone move on its home board, as other boards decide whether it can
+// so attacker win is real one, while defender win here is not, other boards can only lose game for attacker
+#[derive(Clone)]
+pub struct PairGame {
+    boards: [[i8; 36]; 2],
+    attacker_to_move: bool
+}
+
+enum Child {
+    AttackerWin,
+    DefenderWin,
+    Position(PairGame)
+}
+
+impl PairGame {
+    pub fn of(position: &Shobu, attacker: i8, home_board: usize) -> Self {
+        let mut boards = [[MARGIN; 36]; 2];
+        for (board, board_id) in boards.iter_mut().zip([home_board, paired_board(home_board)]) {
+            for tile in TILES {
+                let stone = position.boards[board_id][tile];
+                board[tile] = if stone == attacker { ATTACKER } else if stone == -attacker { DEFENDER } else { EMPTY };
+            }
+        }
+        PairGame { boards, attacker_to_move: position.active_player == attacker }
+    }
+
+    fn children(&self) -> Vec<Child> {
+        let mut children = Vec::new();
+        let mut add = |boards: [[i8; 36]; 2]| {
+            let attacker_lost = boards.iter().any(|x| count(x, ATTACKER) == 0);
+            let defender_lost = boards.iter().any(|x| count(x, DEFENDER) == 0);
+            children.push(if defender_lost { Child::AttackerWin } else if attacker_lost { Child::DefenderWin }
+                else { Child::Position(PairGame { boards, attacker_to_move: !self.attacker_to_move }) });
+        };
+        let (player, passive, aggressive) = if self.attacker_to_move { (ATTACKER, 0, 1) } else { (DEFENDER, 1, 0) };
+        for direction in DIRECTIONS {
+            for double in [false, true] {
+                for from in TILES {
+                    let Some((passive_board, false)) = stone_move(&self.boards[passive], player, from, direction, double) else { continue; };
+                    for to in TILES {
+                        let Some((aggressive_board, _)) = stone_move(&self.boards[aggressive], player, to, direction, double) else { continue; };
+                        let mut boards = self.boards;
+                        boards[passive] = passive_board;
+                        boards[aggressive] = aggressive_board;
+                        add(boards);
+                    }
+                }
+                if self.attacker_to_move { continue; }
+                // home board move with partner on other defender board
+                for from in TILES {
+                    let Some((board, _)) = stone_move(&self.boards[1], player, from, direction, double) else { continue; };
+                    add([self.boards[0], board]);
+                }
+            }
+        }
+        // move on other two boards
+        if !self.attacker_to_move {
+            add(self.boards);
+        }
+        children
+    }
+}
+
+fn paired_board(home_board: usize) -> usize {
+    3 - home_board
+}
+
+fn count(board: &[i8; 36], player: i8) -> usize {
+    TILES.iter().filter(|&&x| board[x] == player).count()
+}
+
+// board after legal stone move with push flag, pushed stone lands on margin when ejected
+fn stone_move(board: &[i8; 36], player: i8, from: usize, direction: i8, double: bool) -> Option<([i8; 36], bool)> {
+    if board[from] != player { return None; }
+    let step = movement::step(from, direction, double);
+    if step.to == OFF_BOARD || board[step.to] == player || board[step.over] == player { return None; }
+    let mut pushed = None;
+    for tile in [step.to, step.over] {
+        if board[tile] != -player { continue; }
+        // double push
+        if pushed.is_some() { return None; }
+        pushed = Some(tile);
+    }
+    let mut result = *board;
+    if let Some(pushed_from) = pushed {
+        // push blocked
+        if board[step.landing] == player || board[step.landing] == -player { return None; }
+        result[pushed_from] = EMPTY;
+        if step.landing != OFF_BOARD { result[step.landing] = -player; }
+    }
+    result[from] = EMPTY;
+    result[step.to] = player;
+    Some((result, pushed.is_some()))
+}
+
+// attacker and defender stone counts of both boards
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Signature(pub [[usize; 2]; 2]);
+
+impl Signature {
+    pub fn of(game: &PairGame) -> Self {
+        Signature(game.boards.map(|x| [count(&x, ATTACKER), count(&x, DEFENDER)]))
+    }
+
+    pub fn from_string(encoded: &str) -> Result<Self, String> {
+        let error = || format!("Invalid signature {}!", encoded);
+        let boards: Vec<&str> = encoded.split('.').collect();
+        if boards.len() != 2 { return Err(error()); }
+        let mut stones = [[0; 2]; 2];
+        for (board_stones, board) in stones.iter_mut().zip(boards) {
+            let (attacker, defender) = board.strip_prefix('a').and_then(|x| x.split_once('d')).ok_or_else(error)?;
+            *board_stones = [attacker.parse().map_err(|_| error())?, defender.parse().map_err(|_| error())?];
+        }
+        let signature = Signature(stones);
+        if !signature.is_supported() { return Err(error()); }
+        Ok(signature)
+    }
+
+    // both boards have both colours, so pair game is not over
+    pub fn is_supported(&self) -> bool {
+        self.0.iter().flatten().all(|&x| (1..=MAX_TABLEBASE_STONES).contains(&x))
+    }
+
+    fn board_size(&self, board: usize) -> u64 {
+        let [attacker, defender] = self.0[board];
+        binomial(16, attacker) * binomial(16 - attacker, defender)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.board_size(0) * self.board_size(1) * 2
+    }
+
+    // signatures reachable by pushing one stone off the board
+    pub fn reductions(&self) -> Vec<Signature> {
+        let mut reductions = Vec::new();
+        for board in 0..2 {
+            for color in 0..2 {
+                let mut stones = self.0;
+                stones[board][color] -= 1;
+                let reduced = Signature(stones);
+                if reduced.is_supported() { reductions.push(reduced); }
+            }
+        }
+        reductions
+    }
+
+    // index of game with both boards seen through one of 8 board symmetries
+    fn index(&self, game: &PairGame, symmetry: usize) -> u64 {
+        let mut index = 0;
+        for (board_id, board) in game.boards.iter().enumerate() {
+            // combinatorial number system ranks of ascending slots
+            let (mut attackers, mut defenders, mut attacker_rank, mut defender_rank) = (0, 0, 0, 0);
+            for (slot, &tile) in SYMMETRIC_TILES[symmetry].iter().enumerate() {
+                match board[tile] {
+                    ATTACKER => {
+                        attackers += 1;
+                        attacker_rank += binomial(slot, attackers);
+                    },
+                    // defender slots are counted among tiles without attacker stones
+                    DEFENDER => {
+                        defenders += 1;
+                        defender_rank += binomial(slot - attackers, defenders);
+                    },
+                    _ => ()
+                }
+            }
+            index = index * self.board_size(board_id) + attacker_rank * binomial(16 - attackers, defenders) + defender_rank;
+        }
+        index * 2 + !game.attacker_to_move as u64
+    }
+
+    pub fn canonical_index(&self, game: &PairGame) -> u64 {
+        (0..8).map(|x| self.index(game, x)).min().unwrap()
+    }
+
+    pub fn position(&self, mut index: u64) -> PairGame {
+        let attacker_to_move = index.is_multiple_of(2);
+        index /= 2;
+        let mut boards = [[MARGIN; 36]; 2];
+        for board_id in (0..2).rev() {
+            let [attacker, defender] = self.0[board_id];
+            let board_index = index % self.board_size(board_id);
+            index /= self.board_size(board_id);
+            let defender_count = binomial(16 - attacker, defender);
+            let mut cells = [EMPTY; 16];
+            for slot in unrank(board_index / defender_count, attacker) { cells[slot] = ATTACKER; }
+            let free_slots: Vec<usize> = (0..16).filter(|&x| cells[x] == EMPTY).collect();
+            for slot in unrank(board_index % defender_count, defender) { cells[free_slots[slot]] = DEFENDER; }
+            for (tile, cell) in TILES.iter().zip(cells) {
+                boards[board_id][*tile] = cell;
+            }
+        }
+        PairGame { boards, attacker_to_move }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let boards: Vec<String> = self.0.iter().map(|[attacker, defender]| format!("a{}d{}", attacker, defender)).collect();
+        write!(f, "{}", boards.join("."))
+    }
+}
+
+// tile seen at every slot of board transformed by one of 8 rotations and reflections, all of them keep set of move directions
+const fn symmetric_tiles() -> [[usize; 16]; 8] {
+    let mut tiles = [[0; 16]; 8];
+    let mut symmetry = 0;
+    while symmetry < 8 {
+        let mut slot = 0;
+        while slot < 16 {
+            let (mut row, mut column) = (slot / 4, slot % 4);
+            if symmetry & 1 == 1 { (row, column) = (column, row); }
+            if symmetry & 2 == 2 { row = 3 - row; }
+            if symmetry & 4 == 4 { column = 3 - column; }
+            tiles[symmetry][row * 4 + column] = TILES[slot];
+            slot += 1;
+        }
+        symmetry += 1;
+    }
+    tiles
+}
+
+static SYMMETRIC_TILES: [[usize; 16]; 8] = symmetric_tiles();
+
+// pascal triangle up to 16 tiles
+const fn binomials() -> [[u64; 17]; 17] {
+    let mut binomials = [[0; 17]; 17];
+    let mut n = 0;
+    while n < 17 {
+        binomials[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            binomials[n][k] = binomials[n - 1][k - 1] + binomials[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    binomials
+}
+
+static BINOMIALS: [[u64; 17]; 17] = binomials();
+
+fn binomial(n: usize, k: usize) -> u64 {
+    BINOMIALS[n][k]
+}
+
+fn unrank(mut rank: u64, count: usize) -> Vec<usize> {
+    let mut slots = vec![0; count];
+    for i in (0..count).rev() {
+        let mut slot = i;
+        while binomial(slot + 1, i + 1) <= rank { slot += 1; }
+        rank -= binomial(slot, i + 1);
+        slots[i] = slot;
+    }
+    slots
+}
+
+// retrograde solved pair games of one signature, symmetric ones share canonical entry
+pub struct Tablebase {
+    signature: Signature,
+    values: Vec<u8>
+}
+
+impl Tablebase {
+    pub fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    pub fn probe(&self, game: &PairGame) -> Option<TablebaseScore> {
+        TablebaseScore::from_value(self.values[self.signature.canonical_index(game) as usize], game.attacker_to_move)
+    }
+
+    // every signature reachable by pushes must be in tablebases, games of unknown signatures stay unproven
+    pub fn generate(signature: Signature, tablebases: &Tablebases) -> Self {
+        let mut table = Tablebase { signature, values: vec![0; signature.size() as usize] };
+        let canonical: Vec<u64> = (0..signature.size()).filter(|&index| {
+            signature.canonical_index(&signature.position(index)) == index
+        }).collect();
+        // distances are found in increasing order, so wins are stored once shortest one is known
+        for distance in 1..=MAX_DISTANCE {
+            let mut solved = Vec::new();
+            let mut pending = false;
+            for &index in &canonical {
+                if table.values[index as usize] != 0 { continue; }
+                match table.solve(&signature.position(index), tablebases) {
+                    Some(TablebaseScore::Win(win)) if win > distance => pending = true,
+                    Some(score) => solved.push((index, score.distance())),
+                    None => ()
+                }
+            }
+            if solved.is_empty() && !pending { break; }
+            for (index, plies) in solved {
+                table.values[index as usize] = (plies + 1) as u8;
+            }
+        }
+        table
+    }
+
+    // result known from already solved children, attacker wins with one of them, defender loses with all
+    fn solve(&self, game: &PairGame, tablebases: &Tablebases) -> Option<TablebaseScore> {
+        let mut win = None;
+        let mut loss = Some(0);
+        for child in game.children() {
+            let child = match child {
+                Child::AttackerWin => Some(0),
+                Child::DefenderWin => None,
+                Child::Position(child) if Signature::of(&child) == self.signature => self.probe(&child).map(|x| x.distance()),
+                Child::Position(child) => tablebases.probe_pair(&child).map(|x| x.distance())
+            };
+            match child {
+                Some(plies) => {
+                    win = Some(usize::min(win.unwrap_or(usize::MAX), plies + 1));
+                    loss = loss.map(|x| usize::max(x, plies + 1));
+                },
+                None => loss = None
+            }
+        }
+        if game.attacker_to_move {
+            win.filter(|&x| x <= MAX_DISTANCE).map(TablebaseScore::Win)
+        } else {
+            loss.filter(|&x| x <= MAX_DISTANCE).map(TablebaseScore::Loss)
+        }
+    }
+
+    // magic, signature and values of canonical games in index order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.signature.0.iter().flatten().map(|&x| x as u8));
+        for index in 0..self.signature.size() {
+            if self.signature.canonical_index(&self.signature.position(index)) == index {
+                bytes.push(self.values[index as usize]);
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < MAGIC.len() + 4 || !bytes.starts_with(MAGIC) { return Err("Not a tablebase!".to_string()); }
+        let mut stones = [[0; 2]; 2];
+        for (i, count) in bytes[MAGIC.len()..MAGIC.len() + 4].iter().enumerate() {
+            stones[i / 2][i % 2] = *count as usize;
+        }
+        let signature = Signature(stones);
+        if !signature.is_supported() { return Err("Invalid tablebase signature!".to_string()); }
+        let mut table = Tablebase { signature, values: vec![0; signature.size() as usize] };
+        let mut values = bytes[MAGIC.len() + 4..].iter();
+        for index in 0..signature.size() {
+            if signature.canonical_index(&signature.position(index)) == index {
+                table.values[index as usize] = *values.next().ok_or("Tablebase is truncated!")?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+pub struct Tablebases {
+    tables: FxHashMap<Signature, Tablebase>
+}
+
+impl Tablebases {
+    pub fn new() -> Self {
+        Tablebases { tables: FxHashMap::default() }
+    }
+
+    pub fn add(&mut self, table: Tablebase) {
+        self.tables.insert(table.signature(), table);
+    }
+
+    pub fn contains(&self, signature: &Signature) -> bool {
+        self.tables.contains_key(signature)
+    }
+
+    fn probe_pair(&self, game: &PairGame) -> Option<TablebaseScore> {
+        self.tables.get(&Signature::of(game))?.probe(game)
+    }
+
+    // proven win of either player on one of its board pairs
+    // it holds when defender can not push all attacker stones off one of other boards before win, one stone per move
+    pub fn probe(&self, position: &Shobu) -> Option<TablebaseScore> {
+        let stones = |player_id: usize, board_id: usize| position.pieces[player_id][board_id].iter().filter(|&&x| x != NOT_ON_BOARD).count();
+        for attacker in [position.active_player, -position.active_player] {
+            let attacker_id = if attacker == BLACK { 0 } else { 1 };
+            for home_board in if attacker == BLACK { [0, 1] } else { [2, 3] } {
+                let boards = [home_board, paired_board(home_board)];
+                // cheap material check before building pair game
+                if boards.iter().any(|&x| stones(0, x) > MAX_TABLEBASE_STONES || stones(1, x) > MAX_TABLEBASE_STONES) { continue; }
+                let Some(score) = self.probe_pair(&PairGame::of(position, attacker, home_board)) else { continue; };
+                let defender_moves = score.distance() / 2;
+                if (0..4).filter(|x| !boards.contains(x)).all(|x| stones(attacker_id, x) > defender_moves) {
+                    return Some(score);
+                }
+            }
+        }
+        None
+    }
+
+    // generates signature with all smaller signatures it depends on, skipping tables already present
+    pub fn generate(&mut self, signature: Signature) {
+        if self.contains(&signature) { return; }
+        for reduced in signature.reductions() {
+            self.generate(reduced);
+        }
+        let table = Tablebase::generate(signature, self);
+        self.add(table);
+    }
+
+    // tables are stored as <signature>.tb files
+    pub fn load_dir(path: &str) -> Result<Self, String> {
+        let mut tablebases = Tablebases::new();
+        for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().is_some_and(|x| x == "tb") {
+                tablebases.add(Tablebase::from_bytes(&fs::read(&path).map_err(|e| e.to_string())?)?);
+            }
+        }
+        Ok(tablebases)
+    }
+
+    pub fn save_dir(&self, path: &str) -> Result<(), String> {
+        fs::create_dir_all(path).map_err(|e| e.to_string())?;
+        for (signature, table) in &self.tables {
+            fs::write(Path::new(path).join(format!("{}.tb", signature)), table.to_bytes()).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
@@ -3,9 +3,29 @@ mod tests {
     use rand::{rngs::StdRng, Rng, SeedableRng};
     use rand::seq::SliceRandom;
     use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Instant;
     use crate::symmetry;
-    use crate::tt_entry::{TTEntry, EXACT};
-    use crate::{bot::ShobuBot, shobu::{self, Shobu, TILES, WHITE}, shobu_move::{internal_2_readable, readable_2_internal, Move}};
+    use crate::book::OpeningBook;
+    use crate::datagen::{Datagen, DatagenSettings};
+    use crate::bot_constants::WIN_EVAL;
+    use crate::eval_params::EvalParams;
+    use crate::mcts::{MctsBot, Playout};
+    use crate::nnue::Network;
+    use crate::player::{self, GreedyBot, Player, RandomBot};
+    use crate::match_runner::{self, EngineConfig, MatchResult, MatchRunner, MatchSettings, SprtVerdict};
+    use crate::search_limits::{self, SearchLimits};
+    use crate::search_result::Score;
+    use crate::tablebase::{PairGame, Signature, TablebaseScore, Tablebases};
+    use crate::time_manager::GameClock;
+    use crate::transposition_table::TranspositionTable;
+    use crate::tuner::{self, Tuner};
+    use crate::tt_entry::{score_from_tt, score_to_tt, Bound, TTEntry};
+    use crate::packed_move::PackedMove;
+    use crate::{bot::ShobuBot, shobu::{self, Shobu, TILES, WHITE}, shobu_move::{internal_2_readable, readable_2_internal, Move, MoveExtended}};
+    use crate::move_list::{MoveKind, StagedMoves};
+    use crate::movement;
 
     #[test]
     fn test_position_strings() {
@@ -41,6 +61,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ejecting_move_generation() {
+        let positions = [
+            "b wwww__________bb wwww___________b wwww___________b wwww___________b",
+            "b w_b_____________ wb______________ wb______________ wb______________",
+            "w ww_w__b_w__bb___ wwwwbb____b____b __ww_w______bbwb _ww_________w__w",
+            "b wwww________bbbb wwww________bbbb wwww________bbbb wwww________bbbb"
+        ];
+        for position in positions {
+            let mut game = Shobu::from_string(position);
+            let stones = |game: &Shobu| game.pieces.iter().flatten().flatten().filter(|&&x| x != shobu::NOT_ON_BOARD).count();
+            let before = stones(&game);
+            let mut expected = 0;
+            for mv in game.get_legal_moves() {
+                game.make_move_unsafe(&mv.mv);
+                if stones(&game) < before { expected += 1; }
+                game.undo_move();
+            }
+            let ejecting = game.get_ejecting_moves();
+            assert_eq!(ejecting.len(), expected);
+            for mv in ejecting {
+                game.make_move_unsafe(&mv.mv);
+                assert_eq!(stones(&game), before - 1);
+                game.undo_move();
+            }
+        }
+    }
+
     #[test]
     fn test_readable_2_internal() {
         for tile in (0..16).into_iter() {
@@ -169,7 +217,7 @@ mod tests {
         for (winner, position) in std::iter::zip(winners, positions) {
             let mut game = Shobu::from_string(position);
             let mut bot = ShobuBot::new();
-            let mv = bot.choose_move(&mut game);
+            let mv = bot.choose_move(&mut game, &SearchLimits::new());
             let mut validation_game = Shobu::from_string(position);
             validation_game.make_move(&mv).unwrap();
             assert_eq!(validation_game.winner, winner);
@@ -258,7 +306,7 @@ mod tests {
 
         let best_move = Move::from_string(encoded_move, -1).unwrap();
         let _ = game1.validate_and_extend(&best_move).unwrap();
-        let entry = TTEntry::new(game1.get_symmetry_hash(false, false), 0.0, EXACT, 3, best_move.deep_copy());
+        let entry = TTEntry::new(game1.get_symmetry_hash(false, false), 0.0, Bound::Exact, 3, best_move.deep_copy());
 
         let (color_swap, horizontal_swap) = symmetry::transposition_symmetries(&game2, &entry).unwrap();
         assert!(!color_swap);
@@ -280,7 +328,7 @@ mod tests {
 
         let best_move = Move::from_string(encoded_move, -1).unwrap();
         let _ = game1.validate_and_extend(&best_move).unwrap();
-        let entry = TTEntry::new(game1.get_symmetry_hash(false, false), 0.0, EXACT, 3, best_move.deep_copy());
+        let entry = TTEntry::new(game1.get_symmetry_hash(false, false), 0.0, Bound::Exact, 3, best_move.deep_copy());
 
         let (color_swap, horizontal_swap) = symmetry::transposition_symmetries(&game2, &entry).unwrap();
         assert!(color_swap);
@@ -291,15 +339,685 @@ mod tests {
         assert_eq!(ext_symm_move.to_string(game2.active_player), expected_symmetric_move);
     }
 
+    #[test]
+    fn test_packed_move_and_tt_entry() {
+        let mut rand = StdRng::seed_from_u64(5);
+        let mut game = Shobu::new();
+        for _ in 0..40 {
+            let moves = game.get_legal_moves();
+            for mv in &moves {
+                let packed = PackedMove::new(&mv.mv);
+                assert_eq!(PackedMove::from_bits(packed.bits()), packed);
+                assert!(packed.to_move().is_same(&mv.mv));
+                assert_eq!(packed.to_move().board_1, mv.mv.board_1);
+            }
+            let played = &moves[rand.gen_range(0..moves.len())].mv;
+            game.make_move_unsafe(played);
+            assert_eq!(game.history.last().unwrap().0, PackedMove::new(played));
+            if game.winner != 0 { break; }
+        }
+
+        let mv = Move::from_string("2DLb2h5", -1).unwrap();
+        for (eval, bound) in [(-1.25, Bound::Upper), (WIN_EVAL - 3.0, Bound::Lower), (0.004, Bound::Exact), (0.127, Bound::Lower), (0.123, Bound::Upper), (-0.127, Bound::Upper)] {
+            let entry = TTEntry::new(0x1234_5678_9abc_def0, eval, bound, 7, mv.deep_copy());
+            let unpacked = TTEntry::from_data(entry.variation_hash, entry.data());
+            assert!((unpacked.eval() - eval).abs() < 0.01);
+            // stored bound never cuts off more than true one
+            match bound {
+                Bound::Exact => assert!((unpacked.eval() - eval).abs() <= 0.005),
+                Bound::Lower => assert!(unpacked.eval() <= eval),
+                Bound::Upper => assert!(unpacked.eval() >= eval)
+            }
+            assert_eq!(unpacked.bound, bound);
+            assert_eq!(unpacked.depth, 7);
+            assert_eq!(unpacked.best_move, PackedMove::new(&mv));
+        }
+    }
+
     #[test]
     fn test_returns_valid_move() {
         let mut game = Shobu::new();
         let mut bot = ShobuBot::new();
-        let mv = bot.choose_move(&mut game);
+        let mv = bot.choose_move(&mut game, &SearchLimits::new());
         let _res = game.validate_and_extend(&mv).unwrap().to_string(game.active_player);
         assert!(true);
     }
 
+    #[test]
+    fn test_multithreaded_win_in_1_move() {
+        let position = "b w_b_____________ wb______________ wb______________ wb______________";
+        let mut game = Shobu::from_string(position);
+        let mut bot = ShobuBot::new();
+        bot.set_threads(4);
+        let mv = bot.choose_move(&mut game, &SearchLimits::new());
+        assert_eq!(game.to_string(), position);
+        game.make_move(&mv).unwrap();
+        assert_eq!(game.winner, -1);
+    }
+
+    #[test]
+    fn test_transposition_table_symmetric_probe() {
+        let position_1 = "b w_b_____________ wb______________ wb______________ w______________b";
+        let position_2 = "b _b_w____________ __bw____________ __bw____________ ___w________b___";
+        let game1 = Shobu::from_string(position_1);
+        let game2 = Shobu::from_string(position_2);
+        let tt = TranspositionTable::new(1024);
+        let best_move = Move::from_string("Db2h1", -1).unwrap();
+        tt.insert(&game1, &TTEntry::new(game1.get_symmetry_hash(false, false), 1.5, Bound::Exact, 3, best_move));
+        let entry = tt.get(&game2).unwrap();
+        assert_eq!(entry.eval(), 1.5);
+        assert_eq!(entry.depth, 3);
+        let (color_swap, horizontal_swap) = symmetry::transposition_symmetries(&game2, &entry).unwrap();
+        let symm_move = entry.best_move.to_move().to_symmetric(color_swap, horizontal_swap);
+        assert_eq!(game2.validate_and_extend(&symm_move).unwrap().to_string(game2.active_player), "Db1h2");
+        assert!(tt.get(&Shobu::new()).is_none());
+        tt.clear();
+        assert!(tt.get(&game2).is_none());
+    }
+
+    #[test]
+    fn test_depth_limit_deterministic() {
+        let position = "b w_www_______bb_b wwww________bbbb wwww________bbbb www__bw_____bbb_";
+        let mut moves = Vec::new();
+        for _ in 0..2 {
+            let mut game = Shobu::from_string(position);
+            let mut bot = ShobuBot::new();
+            let mv = bot.choose_move(&mut game, &SearchLimits::depth(2));
+            moves.push(game.validate_and_extend(&mv).unwrap().to_string(game.active_player));
+        }
+        assert_eq!(moves[0], moves[1]);
+        // cleared table searches like new one
+        let mut bot = ShobuBot::new();
+        let nodes = |bot: &mut ShobuBot| bot.search(&mut Shobu::from_string(position), &SearchLimits::depth(2)).unwrap().iterations.iter().map(|x| x.nodes).sum::<usize>();
+        let first = nodes(&mut bot);
+        assert!(nodes(&mut bot) < first);
+        bot.clear_hash();
+        assert_eq!(nodes(&mut bot), first);
+    }
+
+    #[test]
+    fn test_node_and_stop_limits() {
+        let mut game = Shobu::new();
+        let mut bot = ShobuBot::new();
+        let start = Instant::now();
+        let mv = bot.choose_move(&mut game, &SearchLimits::nodes(5000));
+        game.validate_and_extend(&mv).unwrap();
+        let stop = Arc::new(AtomicBool::new(true));
+        let mv = bot.choose_move(&mut game, &SearchLimits::infinite(stop));
+        game.validate_and_extend(&mv).unwrap();
+        assert!(start.elapsed().as_millis() < 1000);
+        // node limit counts nodes of all threads, each may search one more before seeing it
+        let mut bot = ShobuBot::new();
+        bot.set_threads(4);
+        let result = bot.search(&mut game, &SearchLimits::nodes(5000)).unwrap();
+        assert!(result.iterations.iter().map(|x| x.nodes).sum::<usize>() <= 5000 + 4);
+    }
+
+    #[test]
+    fn test_clock_limits_move_time() {
+        let clock = GameClock::from_string("1000 60000 0 0").unwrap();
+        assert_eq!(clock.time, [1000, 60000]);
+        assert!(GameClock::from_string("1000 60000").is_none());
+        let mut game = Shobu::new();
+        let mut bot = ShobuBot::new();
+        let start = Instant::now();
+        let mv = bot.choose_move(&mut game, &SearchLimits::clock(clock));
+        game.validate_and_extend(&mv).unwrap();
+        // black has 1000 ms left, move must take at most 30% of it
+        assert!(start.elapsed().as_millis() < 400);
+    }
+
+    #[test]
+    fn test_pondering_hit_and_miss() {
+        let position = "b w_www_______bb_b wwww________bbbb wwww________bbbb www__bw_____bbb_";
+        let mut game = Shobu::from_string(position);
+        let mut bot = ShobuBot::new();
+        bot.set_ponder(true);
+        // finished iteration stores reply to ponder on
+        let limits = SearchLimits::depth(2);
+        let mv = bot.choose_move(&mut game, &limits);
+        game.make_move(&mv).unwrap();
+        let moves = game.get_legal_moves();
+        game.undo_move();
+        // hit, pondering stops and search on filled table is done with given limits
+        let reply = bot.start_pondering(&game, &mv).unwrap();
+        game.make_move(&mv).unwrap();
+        game.make_move(&reply).unwrap();
+        let mut hit_game = Shobu::from_string(&game.to_string());
+        let hit = bot.search(&mut hit_game, &limits).unwrap();
+        assert!(hit.ponder_hit);
+        assert_eq!(hit.depth, 2);
+        hit_game.make_move(&hit.best_move).unwrap();
+        // miss
+        game.undo_move();
+        game.undo_move();
+        bot.start_pondering(&game, &mv).unwrap();
+        game.make_move(&mv).unwrap();
+        let other_reply = moves.iter().find(|x| x.to_string(game.active_player) != game.validate_and_extend(&reply).unwrap().to_string(game.active_player)).unwrap();
+        game.make_move(&other_reply.mv).unwrap();
+        let miss = bot.search(&mut game, &limits).unwrap();
+        assert!(!miss.ponder_hit);
+        assert_eq!(miss.depth, hit.depth);
+        game.make_move(&miss.best_move).unwrap();
+    }
+
+    #[test]
+    fn test_pondering_hit_honours_stop() {
+        let mut game = Shobu::from_string("b w_www_______bb_b wwww________bbbb wwww________bbbb www__bw_____bbb_");
+        let mut bot = ShobuBot::new();
+        bot.set_ponder(true);
+        let mv = bot.choose_move(&mut game, &SearchLimits::depth(2));
+        let reply = bot.start_pondering(&game, &mv).unwrap();
+        game.make_move(&mv).unwrap();
+        game.make_move(&reply).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        // long time budget, but stop is already set, so search ends at once
+        let limits = SearchLimits { stop: Some(Arc::new(AtomicBool::new(true))), ..SearchLimits::movetime(60000) };
+        let start = Instant::now();
+        let hit = bot.search(&mut game, &limits).unwrap();
+        assert!(start.elapsed().as_millis() < 1000);
+        assert!(hit.ponder_hit);
+        game.make_move(&hit.best_move).unwrap();
+    }
+
+    #[test]
+    fn test_win_in_n_reporting() {
+        let position = "b w_b_____________ wb______________ wb______________ wb______________";
+        let mut game = Shobu::from_string(position);
+        let mut bot = ShobuBot::new();
+        let result = bot.search(&mut game, &SearchLimits::depth(3)).unwrap();
+        assert_eq!(result.score().to_string(), "win in 1 moves");
+        assert_eq!(result.pv.len(), 1);
+        assert_eq!(Score::from_eval(WIN_EVAL - 3.0).to_string(), "win in 2 moves");
+        assert_eq!(Score::from_eval(-WIN_EVAL + 2.0).to_string(), "loss in 1 moves");
+        assert_eq!(Score::from_eval(1.5).to_string(), "+1.50");
+        // stored relative to position, restored relative to root
+        assert_eq!(score_to_tt(WIN_EVAL - 5.0, 3), WIN_EVAL - 2.0);
+        assert_eq!(score_from_tt(WIN_EVAL - 2.0, 1), WIN_EVAL - 3.0);
+        assert_eq!(score_from_tt(score_to_tt(-WIN_EVAL + 4.0, 2), 2), -WIN_EVAL + 4.0);
+    }
+
+    #[test]
+    fn test_multipv_analysis() {
+        let position = "b w_www_______bb_b wwww________bbbb wwww________bbbb www__bw_____bbb_";
+        let mut game = Shobu::from_string(position);
+        let mut bot = ShobuBot::new();
+        let lines = bot.analyse(&mut game, &SearchLimits::depth(2), 4).unwrap();
+        assert_eq!(lines.len(), 4);
+        let moves: HashSet<&String> = lines.iter().map(|x| &x.mv).collect();
+        assert_eq!(moves.len(), 4);
+        for line in &lines {
+            assert_eq!(line.depth, 2);
+            assert_eq!(&line.pv[0], &line.mv);
+        }
+        let evals: Vec<f64> = lines.iter().map(|x| match x.score { Score::Eval(eval) => eval, _ => panic!() }).collect();
+        assert!(evals.windows(2).all(|x| x[0] >= x[1]));
+        let mut bot = ShobuBot::new();
+        let result = bot.search(&mut Shobu::from_string(position), &SearchLimits::depth(2)).unwrap();
+        assert_eq!(result.eval, evals[0]);
+        // lines are best root moves of the same search
+        let mut root_evals: Vec<f64> = result.root_moves.iter().map(|x| x.1).collect();
+        root_evals.sort_by(|a, b| b.total_cmp(a));
+        assert_eq!(&root_evals[..4], &evals[..]);
+        // no finished iteration, no line is reported
+        let mut bot = ShobuBot::new();
+        let lines = bot.analyse(&mut game, &SearchLimits::nodes(1), 4).unwrap();
+        assert!(lines.is_empty());
+        let result = bot.search(&mut game, &SearchLimits::nodes(1)).unwrap();
+        assert_eq!(result.depth, 0);
+        game.validate_and_extend(&result.best_move).unwrap();
+    }
+
+    #[test]
+    fn test_search_and_exclude_moves() {
+        let position = "b w_www_______bb_b wwww________bbbb wwww________bbbb www__bw_____bbb_";
+        let mut game = Shobu::from_string(position);
+        let mut bot = ShobuBot::new();
+        let requested = ["ULb15f5", "Ub12f5", "Ub15f5"];
+        let mut limits = SearchLimits::depth(2);
+        limits.search_moves = Some(search_limits::parse_moves(&requested.join(","), game.active_player).unwrap());
+        let result = bot.search(&mut game, &limits).unwrap();
+        let mut reported: Vec<String> = result.root_moves.iter().map(|(mv, _)| game.validate_and_extend(mv).unwrap().to_string(game.active_player)).collect();
+        reported.sort();
+        let mut expected = requested.map(|x| x.to_string()).to_vec();
+        expected.sort();
+        assert_eq!(reported, expected);
+        let best = game.validate_and_extend(&result.best_move).unwrap().to_string(game.active_player);
+        assert!(requested.contains(&best.as_str()));
+
+        let mut limits = SearchLimits::depth(2);
+        limits.exclude_moves = search_limits::parse_moves(&best, game.active_player).unwrap();
+        let result = bot.search(&mut game, &limits).unwrap();
+        assert_eq!(result.root_moves.len(), game.get_legal_moves().len() - 1);
+        assert_ne!(game.validate_and_extend(&result.best_move).unwrap().to_string(game.active_player), best);
+        assert!(search_limits::parse_moves("Xb1f2", game.active_player).is_err());
+        // restriction leaving no legal move is not replaced by all moves
+        let mut limits = SearchLimits::depth(2);
+        limits.search_moves = Some(search_limits::parse_moves(&best, game.active_player).unwrap());
+        limits.exclude_moves = search_limits::parse_moves(&best, game.active_player).unwrap();
+        assert!(bot.search(&mut game, &limits).is_err());
+        // move in notation, but illegal in position
+        let mut limits = SearchLimits::depth(1);
+        limits.search_moves = Some(search_limits::parse_moves("Ub12f5,Ub1f1", game.active_player).unwrap());
+        assert_eq!(bot.search(&mut game, &limits).err().unwrap(), "Invalid move Ub1f1!");
+        let mut limits = SearchLimits::depth(1);
+        limits.exclude_moves = search_limits::parse_moves("Ub1f1", game.active_player).unwrap();
+        assert!(bot.search(&mut game, &limits).is_err());
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let mut game = Shobu::from_string("b w_www_______bb_b wwww________bbbb wwww________bbbb www__bw_____bbb_");
+        let mut bot = ShobuBot::new();
+        let result = bot.search(&mut game, &SearchLimits::depth(2)).unwrap();
+        assert_eq!(result.iterations.len(), 2);
+        for (i, stats) in result.iterations.iter().enumerate() {
+            assert_eq!(stats.depth, i + 1);
+            assert!(stats.nodes > 0);
+            assert!(stats.seldepth >= stats.depth);
+            assert!(stats.tt_hits <= stats.tt_probes);
+            assert!(stats.first_move_cutoffs <= stats.beta_cutoffs);
+        }
+        let second = &result.iterations[1];
+        assert_eq!(second.branching_factor, second.nodes as f64 / result.iterations[0].nodes as f64);
+        assert!(second.tt_probes > 0);
+        assert!(second.to_string().starts_with("depth 2"));
+    }
+
+    #[test]
+    fn test_eval_params_text_format() {
+        let params = EvalParams::new();
+        let loaded = EvalParams::from_string(&params.to_string()).unwrap();
+        assert_eq!(loaded.to_string(), params.to_string());
+        let expected = [(0, -10.0), (6, -5.8), (7, 4.9), (12, 8.4), (13, 8.9), (16, 10.4)];
+        for (mobility, score) in expected {
+            assert!((params.board_mobility_score(mobility) - score).abs() < 1e-9);
+        }
+        let changed = EvalParams::from_string("# test\nmaterial -1000 0 10 20 30\nmobility_weight 1.5\n").unwrap();
+        assert_eq!(changed.material, [-1000.0, 0.0, 10.0, 20.0, 30.0]);
+        assert_eq!(changed.mobility_weight, 1.5);
+        assert_eq!(changed.psts, params.psts);
+        assert!(EvalParams::from_string("material 1 2").is_err());
+        assert!(EvalParams::from_string("unknown 1").is_err());
+        assert!(EvalParams::from_string("mobility_weight x").is_err());
+    }
+
+    #[test]
+    fn test_tuner_reduces_error() {
+        let params = EvalParams::new();
+        let vector = tuner::params_to_vector(&params);
+        assert_eq!(vector.len(), 140);
+        assert_eq!(tuner::vector_to_params(&vector, &params).to_string(), params.to_string());
+
+        // results follow material balance
+        let mut rand = StdRng::seed_from_u64(2137);
+        let mut data = String::new();
+        for _ in 0..20 {
+            let mut game = Shobu::new();
+            for _ in 0..rand.gen_range(10..30) {
+                let moves = game.get_legal_moves();
+                game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+                if game.winner != 0 { break; }
+            }
+            let balance: i32 = game.pieces.iter().enumerate()
+                .map(|(i, boards)| boards.iter().flatten().filter(|&&x| x != shobu::NOT_ON_BOARD).count() as i32 * if i == 0 {-1} else {1})
+                .sum();
+            let result = if balance > 0 { 1.0 } else if balance < 0 { 0.0 } else { 0.5 };
+            data.push_str(&format!("{};{}\n", game.to_string(), result));
+        }
+        let positions = tuner::parse_positions(&data).unwrap();
+        assert!(!positions.is_empty());
+        let mut tuner = Tuner::new(positions, params);
+        tuner.fit_scaling();
+        let before = tuner.error();
+        tuner.tune(2, 0.5);
+        assert!(tuner.error() <= before);
+        assert!(tuner::parse_positions("b wwww________bbbb wwww________bbbb wwww________bbbb wwww________bbbb;x").is_err());
+    }
+
+    #[test]
+    fn test_match_statistics() {
+        let result = MatchResult { wins: 120, draws: 40, losses: 40, games: Vec::new() };
+        let (elo, margin) = result.elo();
+        assert!((elo - 147.2).abs() < 0.1);
+        assert!(margin > 0.0 && margin < elo);
+        assert_eq!(result.sprt(0.0, 10.0, 0.05, 0.05), SprtVerdict::AcceptH1);
+        let even = MatchResult { wins: 5000, draws: 0, losses: 5000, games: Vec::new() };
+        assert_eq!(even.elo().0, 0.0);
+        assert_eq!(even.sprt(0.0, 10.0, 0.05, 0.05), SprtVerdict::AcceptH0);
+        let short = MatchResult { wins: 2, draws: 0, losses: 1, games: Vec::new() };
+        assert_eq!(short.sprt(0.0, 10.0, 0.05, 0.05), SprtVerdict::Continue);
+    }
+
+    #[test]
+    fn test_match_runner_swaps_colors() {
+        let config = || EngineConfig { name: "default".to_string(), player: "minimax".to_string(), params: EvalParams::new(), threads: 1 };
+        let settings = MatchSettings {
+            games: 2,
+            limits: SearchLimits::depth(1),
+            max_moves: 10,
+            openings: match_runner::random_openings(1, 4, 7),
+            sprt: None
+        };
+        let result = MatchRunner::new([config(), config()], settings).unwrap().run();
+        assert_eq!(result.total(), 2);
+        assert_eq!(result.games[0].opening, result.games[1].opening);
+        assert_eq!((result.games[0].black, result.games[1].black), (0, 1));
+        for game in &result.games {
+            let mut position = Shobu::from_string(&game.opening);
+            for mv in &game.moves {
+                assert!(position.make_move(&Move::from_string(mv, position.active_player).unwrap()).is_ok());
+            }
+            assert!(game.moves.len() <= 10);
+        }
+    }
+
+    #[test]
+    fn test_opening_book_symmetric_probe() {
+        let game1 = Shobu::from_string("b w_b_____________ ____wb__________ wb______________ w______________b");
+        let game2 = Shobu::from_string("b ______bw________ _b_w____________ ___w________b___ __bw____________");
+        let mut book = OpeningBook::new();
+        book.add_move(&game1, &Move::from_string("DLb2h5", -1).unwrap(), 3);
+        book.add_move(&game1, &Move::from_string("DLb2h5", -1).unwrap(), 2);
+        let book = OpeningBook::from_bytes(&book.to_bytes()).unwrap();
+        assert_eq!(book.position_count(), 1);
+        let mut rand = StdRng::seed_from_u64(2137);
+        let mv = book.probe(&game2, &mut rand).unwrap();
+        assert_eq!(game2.validate_and_extend(&mv).unwrap().to_string(game2.active_player), "DRb6h1");
+        assert!(book.probe(&Shobu::new(), &mut rand).is_none());
+        assert!(OpeningBook::from_bytes(&book.to_bytes()[..10]).is_err());
+        // illegal entry with most weight is never picked
+        let mut book = OpeningBook::new();
+        let illegal = Move::from_string("Ub1f1", -1).unwrap();
+        assert!(game1.validate_and_extend(&illegal).is_err());
+        book.add_move(&game1, &illegal, 1000);
+        book.add_move(&game1, &Move::from_string("DLb2h5", -1).unwrap(), 1);
+        for _ in 0..10 {
+            let mv = book.probe(&game1, &mut rand).unwrap();
+            assert_eq!(game1.validate_and_extend(&mv).unwrap().to_string(game1.active_player), "DLb2h5");
+        }
+    }
+
+    #[test]
+    fn test_opening_book_build_and_play() {
+        let mut bot = ShobuBot::new();
+        let mut book = OpeningBook::new();
+        book.build(&mut bot, &mut Shobu::new(), 2, 2, &SearchLimits::depth(1));
+        assert!(book.position_count() >= 2);
+        bot.set_book(Some(Arc::new(book)));
+        let mut game = Shobu::new();
+        let result = bot.search(&mut game, &SearchLimits::depth(2)).unwrap();
+        assert_eq!(result.depth, 0);
+        assert!(game.make_move(&result.best_move).is_ok());
+    }
+
+    #[test]
+    fn test_mcts_finds_win_in_1_move() {
+        let position = "b w_b_____________ wb______________ wb______________ wb______________";
+        for playout in [Playout::Random, Playout::Eval] {
+            let mut mcts = MctsBot::new(1.41, playout);
+            mcts.set_seed(2137);
+            let mut game = Shobu::from_string(position);
+            let result = mcts.search(&mut game, &SearchLimits::nodes(2000));
+            assert_eq!(result.iterations, 2000);
+            assert_eq!(game.to_string(), position);
+            assert!(game.make_move(&result.best_move).is_ok());
+            assert_eq!(game.winner, shobu::BLACK);
+        }
+    }
+
+    #[test]
+    fn test_mcts_scores_no_legal_moves_as_loss() {
+        // only move filling tile 5 of last board leaves white without passive move
+        let position = "b ____b__________w b______________w wb__bb__________ wb___b__b_______";
+        let mut mcts = MctsBot::new(1.41, Playout::Eval);
+        mcts.set_seed(2137);
+        // zero eval scores other leaves as draws
+        let params = EvalParams::new();
+        mcts.set_params(tuner::vector_to_params(&vec![0.0; tuner::params_to_vector(&params).len()], &params));
+        let mut game = Shobu::from_string(position);
+        let result = mcts.search(&mut game, &SearchLimits::nodes(2000));
+        game.make_move(&result.best_move).unwrap();
+        assert_eq!(game.winner, 0);
+        assert_eq!(game.count_legal_moves(), 0);
+    }
+
+    #[test]
+    fn test_players_are_interchangeable() {
+        let position = "b w_b_____________ wb______________ wb______________ wb______________";
+        let mut players: Vec<Box<dyn Player>> = vec![Box::new(ShobuBot::new()), Box::new(GreedyBot::new(EvalParams::new()))];
+        for player in players.iter_mut() {
+            let mut game = Shobu::from_string(position);
+            let mv = player.choose_move(&mut game, &SearchLimits::depth(1));
+            game.make_move(&mv).unwrap();
+            assert_eq!(game.winner, shobu::BLACK);
+        }
+        assert!(player::create_player("unknown", EvalParams::new(), 1).is_err());
+
+        // greedy bot beats random mover
+        let settings = MatchSettings {
+            games: 2,
+            limits: SearchLimits::depth(1),
+            max_moves: 200,
+            openings: vec![Shobu::new().to_string()],
+            sprt: None
+        };
+        let players: [Box<dyn Player>; 2] = [Box::new(GreedyBot::new(EvalParams::new())), Box::new(RandomBot::new(2137))];
+        let result = MatchRunner::with_players(players, settings).run();
+        assert_eq!(result.total(), 2);
+        assert!(result.wins >= 1);
+    }
+
+    #[test]
+    fn test_nnue_incremental_accumulator() {
+        let network = Arc::new(Network::random(&[16, 8, 1], 2137).unwrap());
+        let loaded = Network::from_string(&network.to_string()).unwrap();
+        assert!(Network::from_string("layers 16 2").is_err());
+        let mut rand = StdRng::seed_from_u64(2137);
+        let mut game = Shobu::new();
+        game.set_network(Some(Arc::clone(&network)));
+        for _ in 0..40 {
+            let moves = game.get_legal_moves();
+            game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+            if game.winner != 0 { break; }
+            let incremental = game.nnue.as_ref().unwrap().evaluate();
+            assert!((incremental - network.evaluate(&game)).abs() < 1e-4);
+            assert!((incremental - loaded.evaluate(&game)).abs() < 1e-4);
+        }
+        while !game.history.is_empty() {
+            game.undo_move();
+        }
+        assert!((game.nnue.as_ref().unwrap().evaluate() - network.evaluate(&Shobu::new())).abs() < 1e-4);
+
+        let mut bot = ShobuBot::new();
+        bot.set_network(Some(network));
+        let mut game = Shobu::new();
+        assert!((bot.eval(&game) - loaded.evaluate(&game)).abs() < 1e-4);
+        let mv = bot.choose_move(&mut game, &SearchLimits::depth(2));
+        assert!(game.make_move(&mv).is_ok());
+    }
+
+    #[test]
+    fn test_datagen_records() {
+        let mut bot = ShobuBot::new();
+        // quiet positions are rare, this opening reaches some
+        let mut datagen = Datagen::new(DatagenSettings { limits: SearchLimits::depth(1), max_moves: 200 });
+        let opening = match_runner::random_openings(3, 6, 2137).remove(2);
+        let records = datagen.play_game(&mut bot, &opening);
+        assert!(!records.is_empty());
+        let positions = tuner::parse_positions(&records.join("\n")).unwrap();
+        assert_eq!(positions.len(), records.len());
+        for (record, labelled) in records.iter().zip(&positions) {
+            let fields: Vec<&str> = record.split(';').collect();
+            assert_eq!(fields.len(), 4);
+            assert!(fields[2].parse::<f64>().is_ok());
+            let position = &labelled.position;
+            assert!(Move::from_string(fields[3], position.active_player).is_some());
+            assert!(position.get_ejecting_moves().is_empty());
+        }
+        let hashes: HashSet<u64> = positions.iter().map(|x| x.position.get_hash()).collect();
+        assert_eq!(hashes.len(), positions.len());
+        // same game gives only already written positions
+        assert!(datagen.play_game(&mut bot, &opening).is_empty());
+        // also in new run appending to written records
+        let mut datagen = Datagen::new(DatagenSettings { limits: SearchLimits::depth(1), max_moves: 200 });
+        datagen.add_seen(&records.join("\n"));
+        assert!(datagen.play_game(&mut bot, &opening).is_empty());
+    }
+
+    #[test]
+    fn test_eval_breakdown_sums_to_eval() {
+        let bot = ShobuBot::new();
+        let params = EvalParams::new();
+        let mut rand = StdRng::seed_from_u64(2137);
+        let mut game = Shobu::new();
+        for _ in 0..30 {
+            let breakdown = bot.explain_eval(&game);
+            assert!((breakdown.total() - bot.eval(&game)).abs() < 1e-9);
+            for (board_id, player) in [(0, shobu::BLACK), (1, shobu::BLACK), (2, WHITE), (3, WHITE)] {
+                let color = if player == WHITE {1} else {0};
+                assert_eq!(breakdown.passive_directions[color][board_id], game.available_passive_directions(board_id, player));
+                let directions = game.available_move_directions(board_id, player);
+                assert_eq!(breakdown.move_directions[color][board_id], directions);
+                assert!((breakdown.mobility[color][board_id] - player as f64 * params.board_mobility_score(directions)).abs() < 1e-9);
+            }
+            assert!(breakdown.to_string().contains("total"));
+            let moves = game.get_legal_moves();
+            game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+            if game.winner != 0 { break; }
+        }
+    }
+
+    #[test]
+    fn test_board_role_psts() {
+        assert_eq!([0, 1, 2, 3].map(|board_id| EvalParams::pst_role(0, board_id)), [0, 1, 2, 3]);
+        assert_eq!([0, 1, 2, 3].map(|board_id| EvalParams::pst_role(1, board_id)), [2, 3, 0, 1]);
+        let game = Shobu::new();
+        let default = ShobuBot::new().eval(&game);
+
+        // legacy single tables fill every role, role tables override one board
+        let tiles = vec!["1"; 16].join(" ");
+        let params = EvalParams::from_string(&format!("pst_black {}
+pst_white_home_light {}
+", tiles, vec!["2"; 16].join(" "))).unwrap();
+        assert_eq!(params.psts[0][3][shobu::TILES[0]], 1.0);
+        assert_eq!(params.psts[1][1][shobu::TILES[0]], 2.0);
+        assert_eq!(params.psts_endgame[0][2][shobu::TILES[5]], 1.0);
+        assert_eq!(EvalParams::from_string(&params.to_string()).unwrap().to_string(), params.to_string());
+        assert!(EvalParams::from_string(&format!("pst_white_home_grey {}", tiles)).is_err());
+        let mut bot = ShobuBot::new();
+        bot.set_params(params);
+        let breakdown = bot.explain_eval(&game);
+        assert!((breakdown.psts[0][0] + 4.0).abs() < 1e-9);
+        assert!((breakdown.psts[1][3] - 8.0).abs() < 1e-9);
+
+        // endgame tables are interpolated by stones left
+        let mut params = EvalParams::from_string(&format!("pst_tapered 1
+pst_endgame_black_home_dark {}
+", tiles)).unwrap();
+        assert_eq!(tuner::params_to_vector(&params).len(), 268);
+        let mut bot = ShobuBot::new();
+        bot.set_params(params.clone());
+        assert!((bot.eval(&game) - default).abs() < 1e-9);
+        let endgame = Shobu::from_string("w b_______________ ________________ w_______________ ________________");
+        assert!((bot.explain_eval(&endgame).phase - 2.0 / 32.0).abs() < 1e-9);
+        assert!((params.pst_score(0, 0, shobu::TILES[0], 0.0) - 1.0).abs() < 1e-9);
+        params.pst_tapered = false;
+        assert_eq!(params.pst_score(0, 0, shobu::TILES[0], 0.0), params.psts[0][0][shobu::TILES[0]]);
+    }
+
+    #[test]
+    fn test_threats() {
+        let game = Shobu::from_string("b w_b_____________ wb______________ wb______________ wb______________");
+        let threats = game.threats(shobu::BLACK);
+        assert_eq!(threats.pushes, 2);
+        assert_eq!(threats.ejectable.map(|x| x.count_ones()), [0, 1, 0, 1]);
+        assert_eq!(game.threats(WHITE).ejectable, [0; 4]);
+        let breakdown = ShobuBot::new().explain_eval(&game);
+        assert_eq!(breakdown.ejectable[1], [0, 1, 0, 1]);
+        assert!(breakdown.threats[1][1] < 0.0);
+
+        let mut rand = StdRng::seed_from_u64(42);
+        let mut game = Shobu::new();
+        for _ in 0..40 {
+            let threats = game.threats(game.active_player);
+            let ejecting = game.get_ejecting_moves();
+            assert_eq!(threats.ejectable.iter().any(|x| *x != 0), !ejecting.is_empty());
+            let moves = game.get_legal_moves();
+            game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+            if game.winner != 0 { break; }
+        }
+    }
+
+    #[test]
+    fn test_tablebase_indexing() {
+        let signature = Signature::from_string("a2d1.a1d2").unwrap();
+        assert_eq!(signature.to_string(), "a2d1.a1d2");
+        assert_eq!(signature.size(), 120 * 14 * 16 * 105 * 2);
+        assert_eq!(signature.reductions().len(), 2);
+        assert!(Signature::from_string("a0d1.a1d1").is_err());
+        assert!(Signature::from_string("a3d1.a1d1").is_err());
+        let mut rand = StdRng::seed_from_u64(2137);
+        for _ in 0..200 {
+            let index = rand.gen_range(0..signature.size());
+            let game = signature.position(index);
+            assert_eq!(Signature::of(&game), signature);
+            let canonical = signature.canonical_index(&game);
+            assert!(canonical <= index);
+            assert_eq!(signature.canonical_index(&signature.position(canonical)), canonical);
+        }
+        // both boards transposed, white attacker with colours swapped
+        let game1 = Shobu::from_string("b bw______________ bbbb________wwww bbbb________wwww _____b_________w");
+        let game2 = Shobu::from_string("b b___w___________ bbbb________wwww bbbb________wwww _____b_________w");
+        let game3 = Shobu::from_string("w _____w_________b bbbb________wwww bbbb________wwww wb______________");
+        let pair1 = PairGame::of(&game1, shobu::BLACK, 0);
+        let signature = Signature::of(&pair1);
+        for pair2 in [PairGame::of(&game2, shobu::BLACK, 0), PairGame::of(&game3, WHITE, 3)] {
+            assert_eq!(Signature::of(&pair2), signature);
+            assert_eq!(signature.canonical_index(&pair1), signature.canonical_index(&pair2));
+        }
+        assert!(Tablebases::new().probe(&game1).is_none());
+        assert_eq!(TablebaseScore::Win(3).eval(2), WIN_EVAL - 5.0);
+        assert_eq!(TablebaseScore::Loss(2).eval(2), -WIN_EVAL + 4.0);
+    }
+
+    #[test]
+    fn test_tablebase_probe_in_search() {
+        let signature = Signature::from_string("a1d1.a1d1").unwrap();
+        let mut tablebases = Tablebases::new();
+        tablebases.generate(signature);
+        assert!(tablebases.contains(&signature));
+        let tablebases = Arc::new(tablebases);
+        let mut rand = StdRng::seed_from_u64(2137);
+        let (mut hits, mut tb_hits) = (0, 0);
+        for _ in 0..200 {
+            // one stone of each colour on every board
+            let boards: Vec<String> = (0..4).map(|_| {
+                let tiles: Vec<usize> = (0..16).collect::<Vec<usize>>().choose_multiple(&mut rand, 2).copied().collect();
+                (0..16).map(|x| if x == tiles[0] { 'b' } else if x == tiles[1] { 'w' } else { '_' }).collect()
+            }).collect();
+            let player = if rand.gen_bool(0.5) { 'b' } else { 'w' };
+            let mut game = Shobu::from_string(&format!("{} {}", player, boards.join(" ")));
+            let Some(score) = tablebases.probe(&game) else { continue; };
+            hits += 1;
+            // proven result holds in real game
+            let (TablebaseScore::Win(distance) | TablebaseScore::Loss(distance)) = score;
+            let result = ShobuBot::new().search(&mut game, &SearchLimits::depth(distance)).unwrap();
+            match score {
+                TablebaseScore::Win(_) => assert!(result.eval >= score.eval(0)),
+                TablebaseScore::Loss(_) => assert!(result.eval <= score.eval(0))
+            }
+            // probed below root with the same result
+            let mut bot = ShobuBot::new();
+            bot.set_tablebases(Some(Arc::clone(&tablebases)));
+            let probed = bot.search(&mut game, &SearchLimits::depth(distance + 1)).unwrap();
+            assert_eq!(probed.eval, result.eval);
+            tb_hits += probed.iterations.iter().map(|x| x.tb_hits).sum::<usize>();
+        }
+        assert!(hits > 0);
+        assert!(tb_hits > 0);
+    }
+
     fn generate_segment<R: Rng>(rng: &mut R) -> String {
         let mut segment = Vec::new();
         let w_len = rng.gen_range(1..=4);
@@ -379,6 +1097,87 @@ mod tests {
         assert_eq!(game.available_passive_directions(2, 1), 16);
     }
 
+    #[test]
+    fn test_movement_tables() {
+        let playable = |tile: i8| TILES.contains(&(tile as usize));
+        for from in TILES {
+            for direction in shobu::DIRECTIONS {
+                for double in [false, true] {
+                    let step = movement::step(from, direction, double);
+                    let to = from as i8 + if double { 2 * direction } else { direction };
+                    if !playable(to) {
+                        assert_eq!(step.to, movement::OFF_BOARD);
+                        continue;
+                    }
+                    assert_eq!(step.to, to as usize);
+                    assert_eq!(step.over, if double { (from as i8 + direction) as usize } else { movement::OFF_BOARD });
+                    let landing = to + direction;
+                    assert_eq!(step.landing, if playable(landing) { landing as usize } else { movement::OFF_BOARD });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_list_and_staged_moves() {
+        let mut rand = StdRng::seed_from_u64(99);
+        let mut game = Shobu::new();
+        for _ in 0..60 {
+            let moves = game.get_legal_moves();
+            let list = game.legal_move_list();
+            assert_eq!(game.count_legal_moves(), moves.len());
+            assert!(moves.iter().zip(list.iter()).all(|(x, y)| x.mv.is_same(&y.mv)));
+            let kinds = [MoveKind::EjectingPush, MoveKind::Push, MoveKind::Quiet];
+            assert_eq!(kinds.iter().map(|&x| game.move_list(x).len()).sum::<usize>(), moves.len());
+
+            // hash move first and once, then pushes ejecting stones, other pushes and quiet moves
+            let tt_move = moves.last().map(|x| MoveExtended::new(&x.mv, x.push_1, x.push_2));
+            let mut staged = StagedMoves::new(tt_move);
+            let mut generated = Vec::new();
+            while let Some(mv) = staged.next(&game) {
+                generated.push(mv);
+            }
+            assert_eq!(generated.len(), moves.len());
+            if let Some(last) = moves.last() { assert!(generated[0].mv.is_same(&last.mv)); }
+            let ejecting = game.get_ejecting_moves();
+            let stages: Vec<usize> = generated.iter().skip(1).map(|mv| {
+                if ejecting.iter().any(|x| x.mv.is_same(&mv.mv)) { 0 } else if mv.push_1 || mv.push_2 { 1 } else { 2 }
+            }).collect();
+            assert!(stages.windows(2).all(|x| x[0] <= x[1]));
+            if moves.is_empty() { break; }
+            game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+            if game.winner != 0 { break; }
+        }
+    }
+
+    #[test]
+    fn test_available_move_directions() {
+        let mut rand = StdRng::seed_from_u64(7);
+        let mut game = Shobu::new();
+        for _ in 0..60 {
+            // passive part of legal move is non push move on home board
+            let player = game.active_player;
+            let home_boards = if player == WHITE { [2, 3] } else { [0, 1] };
+            let mut options = HashSet::new();
+            for mv in game.get_legal_moves() {
+                for (board_id, push) in [(mv.mv.board_1, mv.push_1), (mv.mv.board_2, mv.push_2)] {
+                    if home_boards.contains(&board_id) && !push {
+                        options.insert((board_id, mv.mv.direction, mv.mv.double));
+                    }
+                }
+            }
+            for board_id in home_boards {
+                let expected = options.iter().filter(|x| x.0 == board_id).count();
+                assert_eq!(game.available_move_directions(board_id, player), expected);
+                assert!(expected <= game.available_passive_directions(board_id, player));
+            }
+            let moves = game.get_legal_moves();
+            if moves.is_empty() { break; }
+            game.make_move_unsafe(&moves[rand.gen_range(0..moves.len())].mv);
+            if game.winner != 0 { break; }
+        }
+    }
+
     #[test]
     fn test_move_color_symmetry() {
         let game = Shobu::new();
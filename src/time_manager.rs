@@ -0,0 +1,83 @@
+use crate::bot_constants::*;
+use crate::shobu::BLACK;
+
+// remaining time and increment in ms, indexed black, white
+#[derive(Clone, Copy)]
+pub struct GameClock {
+    pub time: [u128; 2],
+    pub increment: [u128; 2]
+}
+
+impl GameClock {
+    pub fn new(black_time: u128, white_time: u128, black_increment: u128, white_increment: u128) -> Self {
+        GameClock {
+            time: [black_time, white_time],
+            increment: [black_increment, white_increment]
+        }
+    }
+
+    // "btime wtime binc winc" in ms
+    pub fn from_string(string: &str) -> Option<Self> {
+        let values: Vec<u128> = string.split_whitespace().map(|x| x.parse().ok()).collect::<Option<_>>()?;
+        if values.len() != 4 { return None; }
+        Some(GameClock::new(values[0], values[1], values[2], values[3]))
+    }
+}
+
+// splits clock into per move budget, adjusted after every finished iteration
+#[derive(Clone)]
+pub struct TimeManager {
+    optimum: u128,
+    maximum: u128,
+    stability_factor: f64,
+    previous_best: Option<usize>,
+    previous_eval: f64
+}
+
+impl TimeManager {
+    pub fn new(clock: &GameClock, active_player: i8) -> Self {
+        let player_id = if active_player == BLACK {0} else {1};
+        let remaining = clock.time[player_id].saturating_sub(MOVE_OVERHEAD);
+        let increment = clock.increment[player_id];
+        let optimum = remaining / MOVES_TO_GO + increment * 3 / 4;
+        let maximum = u128::min(remaining * MAX_TIME_PERCENTAGE / 100, optimum * MAX_TIME_FACTOR);
+        TimeManager {
+            optimum: u128::min(optimum, maximum),
+            maximum,
+            stability_factor: 1.0,
+            previous_best: None,
+            previous_eval: 0.0
+        }
+    }
+
+    pub fn optimum(&self) -> u128 {
+        self.optimum
+    }
+
+    pub fn maximum(&self) -> u128 {
+        self.maximum
+    }
+
+    // called after finished iteration, returns whether next one should be started
+    pub fn continue_search(&mut self, elapsed: u128, best: usize, best_eval: f64, second_eval: f64) -> bool {
+        if let Some(previous_best) = self.previous_best {
+            if previous_best != best {
+                self.stability_factor = f64::min(self.stability_factor * BEST_MOVE_CHANGE_FACTOR, MAX_STABILITY_FACTOR);
+            } else {
+                self.stability_factor = f64::max(self.stability_factor * BEST_MOVE_STABLE_FACTOR, 1.0);
+            }
+            if self.previous_eval - best_eval > SCORE_DROP_MARGIN {
+                self.stability_factor = f64::min(self.stability_factor * SCORE_DROP_FACTOR, MAX_STABILITY_FACTOR);
+            }
+        }
+        self.previous_best = Some(best);
+        self.previous_eval = best_eval;
+        let mut budget = self.optimum as f64 * self.stability_factor;
+        // single root move far better than others
+        if best_eval - second_eval > DOMINANCE_MARGIN {
+            budget *= DOMINANCE_FACTOR;
+        }
+        // next iteration would most likely not finish in budget
+        (elapsed as f64) < budget * NEXT_ITERATION_PERCENTAGE && elapsed < self.maximum
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::shobu::Shobu;
+use crate::symmetry;
+use crate::tt_entry::TTEntry;
+
+// lock-free table shared by search threads
+// each slot keeps variation hash xored with packed entry and the entry, so torn writes are detected on read
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>
+}
+
+impl TranspositionTable {
+    pub fn new(size: usize) -> Self {
+        TranspositionTable {
+            slots: (0..size).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect()
+        }
+    }
+
+    pub fn get(&self, position: &Shobu) -> Option<TTEntry> {
+        let slot = &self.slots[self.index(position.get_hash())];
+        let check = slot[0].load(Ordering::Relaxed);
+        let data = slot[1].load(Ordering::Relaxed);
+        let entry = TTEntry::from_data(check ^ data, data);
+        // empty slot, other position or write in progress
+        symmetry::transposition_symmetries(position, &entry)?;
+        Some(entry)
+    }
+
+    pub fn insert(&self, position: &Shobu, entry: &TTEntry) {
+        let slot = &self.slots[self.index(position.get_hash())];
+        let data = entry.data();
+        slot[0].store(entry.variation_hash ^ data, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot[0].store(0, Ordering::Relaxed);
+            slot[1].store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.slots.len() as u64) as usize
+    }
+}
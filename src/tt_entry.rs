@@ -1,25 +1,77 @@
+use crate::bot_constants::{MAX_PLY, WIN_EVAL};
+use crate::packed_move::PackedMove;
 use crate::shobu_move::Move;
 
-pub const EXACT: usize = 0;
-pub const LOWERBOUND: usize = 1;
-pub const UPPERBOUND: usize = 2;
+// scores are stored in hundredths
+const SCORE_SCALE: f64 = 100.0;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper
+}
+
+// packed into data word: score 32 bits, move 16 bits, depth 8 bits and bound 2 bits
+#[derive(Clone, Copy)]
 pub struct TTEntry {
     pub variation_hash: u64,
-    pub eval: f64,
-    pub flag: usize,
-    pub depth: usize,
-    pub best_move: Move
+    pub score: i32,
+    pub bound: Bound,
+    pub depth: u8,
+    pub best_move: PackedMove
 }
 
 impl TTEntry {
-    pub fn new(hash: u64, eval: f64, flag: usize, depth: usize, best_move: Move) -> Self {
+    pub fn new(hash: u64, eval: f64, bound: Bound, depth: usize, best_move: Move) -> Self {
+        // rounded away from true score, so bounds stay valid
+        let scaled = eval * SCORE_SCALE;
+        let score = match bound {
+            Bound::Exact => scaled.round(),
+            Bound::Lower => scaled.floor(),
+            Bound::Upper => scaled.ceil()
+        };
         TTEntry {
             variation_hash: hash,
-            eval: eval,
-            flag,
-            best_move: best_move,
-            depth: depth
+            score: score as i32,
+            bound,
+            depth: depth.min(u8::MAX as usize) as u8,
+            best_move: PackedMove::new(&best_move)
         }
     }
+
+    pub fn eval(&self) -> f64 {
+        self.score as f64 / SCORE_SCALE
+    }
+
+    pub fn data(&self) -> u64 {
+        let bound = match self.bound { Bound::Exact => 0, Bound::Lower => 1, Bound::Upper => 2 };
+        self.score as u32 as u64
+            | (self.best_move.bits() as u64) << 32
+            | (self.depth as u64) << 48
+            | bound << 56
+    }
+
+    pub fn from_data(hash: u64, data: u64) -> Self {
+        TTEntry {
+            variation_hash: hash,
+            score: data as u32 as i32,
+            bound: match data >> 56 & 3 { 0 => Bound::Exact, 1 => Bound::Lower, _ => Bound::Upper },
+            depth: (data >> 48 & 255) as u8,
+            best_move: PackedMove::from_bits((data >> 32 & 0xFFFF) as u16)
+        }
+    }
+}
+
+// win scores are stored as distance from position, not from root
+pub fn score_to_tt(eval: f64, ply: usize) -> f64 {
+    if eval >= WIN_EVAL - MAX_PLY as f64 { eval + ply as f64 }
+    else if eval <= -WIN_EVAL + MAX_PLY as f64 { eval - ply as f64 }
+    else { eval }
+}
+
+pub fn score_from_tt(eval: f64, ply: usize) -> f64 {
+    if eval >= WIN_EVAL - MAX_PLY as f64 { eval - ply as f64 }
+    else if eval <= -WIN_EVAL + MAX_PLY as f64 { eval + ply as f64 }
+    else { eval }
 }
@@ -0,0 +1,148 @@
+use std::fs;
+use crate::bot::ShobuBot;
+use crate::eval_params::EvalParams;
+use crate::shobu::{Shobu, TILES};
+use crate::utils::sigmoid;
+
+const SCALING_STEPS: usize = 30;
+const INITIAL_SCALING: f64 = 0.1;
+const INITIAL_SCALING_STEP: f64 = 0.02;
+
+// position with game result from white perspective, 1.0 win, 0.5 draw, 0.0 loss
+pub struct LabelledPosition {
+    pub position: Shobu,
+    pub result: f64
+}
+
+// texel method, fits eval through sigmoid to game results by coordinate descent
+pub struct Tuner {
+    bot: ShobuBot,
+    positions: Vec<LabelledPosition>,
+    scaling: f64
+}
+
+// lines "position;result", further fields are ignored
+pub fn parse_positions(string: &str) -> Result<Vec<LabelledPosition>, String> {
+    let mut positions = Vec::new();
+    for line in string.lines() {
+        if line.trim().is_empty() { continue; }
+        let mut fields = line.split(';');
+        let position = Shobu::from_string(fields.next().unwrap().trim());
+        let result: f64 = fields.next()
+            .and_then(|x| x.trim().parse().ok())
+            .ok_or(format!("Invalid result in line {}!", line))?;
+        // finished games are not evaluated
+        if position.winner != 0 { continue; }
+        positions.push(LabelledPosition { position, result });
+    }
+    Ok(positions)
+}
+
+// tuned values, piece square tables of playable tiles with endgame ones only when tapered, material without empty board, mobility curve and threats
+pub fn params_to_vector(params: &EvalParams) -> Vec<f64> {
+    let mut vector = Vec::new();
+    let psts = if params.pst_tapered { vec![params.psts, params.psts_endgame] } else { vec![params.psts] };
+    for pst in psts.iter().flatten().flatten() {
+        vector.extend(TILES.map(|tile| pst[tile]));
+    }
+    vector.extend(&params.material[1..]);
+    vector.extend([params.mobility_low_penalty, params.mobility_weight, params.mobility_high_base, params.mobility_high_weight]);
+    vector.push(params.threat_ejectable);
+    vector.extend(params.threat_low_material);
+    vector.push(params.threat_pushes);
+    vector
+}
+
+pub fn vector_to_params(vector: &[f64], base: &EvalParams) -> EvalParams {
+    let mut params = base.clone();
+    let mut values = vector.iter().copied();
+    let tapered = params.pst_tapered;
+    let mut psts = vec![&mut params.psts];
+    if tapered { psts.push(&mut params.psts_endgame); }
+    for pst in psts.into_iter().flatten().flatten() {
+        for tile in TILES {
+            pst[tile] = values.next().unwrap();
+        }
+    }
+    for material in params.material[1..].iter_mut() {
+        *material = values.next().unwrap();
+    }
+    params.mobility_low_penalty = values.next().unwrap();
+    params.mobility_weight = values.next().unwrap();
+    params.mobility_high_base = values.next().unwrap();
+    params.mobility_high_weight = values.next().unwrap();
+    params.threat_ejectable = values.next().unwrap();
+    for threat in params.threat_low_material.iter_mut() {
+        *threat = values.next().unwrap();
+    }
+    params.threat_pushes = values.next().unwrap();
+    params
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<LabelledPosition>, params: EvalParams) -> Self {
+        Tuner { bot: ShobuBot::evaluator(params), positions, scaling: INITIAL_SCALING }
+    }
+
+    pub fn load(path: &str, params: EvalParams) -> Result<Self, String> {
+        let string = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Ok(Self::new(parse_positions(&string)?, params))
+    }
+
+    pub fn error(&self) -> f64 {
+        if self.positions.is_empty() { return 0.0; }
+        let sum: f64 = self.positions.iter().map(|x| {
+            let predicted = sigmoid(self.bot.eval(&x.position), self.scaling);
+            (x.result - predicted).powi(2)
+        }).sum();
+        sum / self.positions.len() as f64
+    }
+
+    // sigmoid scaling minimizing error of initial parameters, kept fixed while tuning
+    pub fn fit_scaling(&mut self) -> f64 {
+        let mut best = (self.scaling, self.error());
+        let mut step = INITIAL_SCALING_STEP;
+        for _ in 0..SCALING_STEPS {
+            let mut improved = false;
+            for candidate in [best.0 - step, best.0 + step] {
+                if candidate <= 0.0 { continue; }
+                self.scaling = candidate;
+                let error = self.error();
+                if error < best.1 {
+                    best = (candidate, error);
+                    improved = true;
+                }
+            }
+            if !improved { step /= 2.0; }
+        }
+        self.scaling = best.0;
+        self.scaling
+    }
+
+    // changes every parameter by step while error improves, returns tuned parameters
+    pub fn tune(&mut self, iterations: usize, step: f64) -> EvalParams {
+        let base = self.bot.params().clone();
+        let mut vector = params_to_vector(&base);
+        let mut best_error = self.error();
+        for _ in 0..iterations {
+            let mut improved = false;
+            for i in 0..vector.len() {
+                for delta in [step, -step] {
+                    vector[i] += delta;
+                    self.bot.set_params(vector_to_params(&vector, &base));
+                    let error = self.error();
+                    if error < best_error {
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                    vector[i] -= delta;
+                }
+            }
+            if !improved { break; }
+        }
+        let params = vector_to_params(&vector, &base);
+        self.bot.set_params(params.clone());
+        params
+    }
+}
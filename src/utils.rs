@@ -12,3 +12,13 @@ pub fn input(stdin: &Stdin) -> String {
     stdin.read_line(&mut buffer).unwrap();
     strip(&buffer).to_string()
 }
+
+pub fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let position = args.iter().position(|x| x == name)?;
+    args.get(position + 1).cloned()
+}
+
+// maps eval to expected result between 0 and 1
+pub fn sigmoid(eval: f64, scaling: f64) -> f64 {
+    1.0 / (1.0 + f64::exp(-scaling * eval))
+}